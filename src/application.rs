@@ -1,12 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs;
 use std::io::{self};
 use std::num::ParseIntError;
 use std::path::{Path, PathBuf};
 
-use crate::{ParseError, ParseResult};
-use crate::ufw::{Protocol, UfwPort};
+use crate::{ParseError, ParseResult, Protocol};
+use crate::ufw::{RuleDirection, RuleEntry, RuleType, UfwPort};
 
 #[derive(Debug)]
 pub struct ApplicationEntry {
@@ -14,39 +14,107 @@ pub struct ApplicationEntry {
     pub(crate) title: String,
     pub(crate) description: String,
     pub(crate) ports: Vec<ParseResult<UfwPort>>,
+    // from `# label: 443 HTTPS` comments within the profile's section, ufw itself ignores them
+    pub(crate) port_labels: HashMap<u16, String>,
 }
 
 impl ApplicationEntry {
-    fn parse(entry_key: &String, values: &HashMap<String, Option<String>>) -> ParseResult<Self> {
+    fn parse(path: &Path, entry_key: &String, values: &HashMap<String, Option<String>>, section: RawSection) -> ParseResult<Self> {
+        let in_file = |err: ParseError| err.in_file(path, &section.name);
+
         let title: String = values
             .get("title")
-            .ok_or(ParseError::MissingTitle)?.to_owned()
-            .ok_or(ParseError::MissingTitle)?;
+            .ok_or(ParseError::MissingTitle).map_err(in_file)?.to_owned()
+            .ok_or(ParseError::MissingTitle).map_err(in_file)?;
         let description: String = values
             .get("description")
-            .ok_or(ParseError::MissingDescription)?.to_owned()
-            .ok_or(ParseError::MissingDescription)?;
+            .ok_or(ParseError::MissingDescription).map_err(in_file)?.to_owned()
+            .ok_or(ParseError::MissingDescription).map_err(in_file)?;
 
-        let ports = values
+        let ports: String = values
             .get("ports")
-            .ok_or(ParseError::MissingPorts)?.to_owned()
-            .ok_or(ParseError::MissingPorts)?
-            .split("|")
+            .ok_or(ParseError::MissingPorts).map_err(in_file)?.to_owned()
+            .ok_or(ParseError::MissingPorts).map_err(in_file)?;
+        // groups are separated by `|`, some profiles use whitespace instead. `,` only separates ports within a group
+        let ports = strip_comment(&ports)
+            .split(|c: char| c == '|' || c.is_whitespace())
+            .filter(|group| !group.is_empty())
             .map(ApplicationEntry::parse_ports)
             .flatten()
+            .map(|port| port.map_err(in_file))
             .collect::<Vec<ParseResult<UfwPort>>>();
         if ports.is_empty() {
-            Err(ParseError::EmptyPortsSection)?
+            Err(in_file(ParseError::EmptyPortsSection))?
         }
 
         Ok(ApplicationEntry {
-            name: entry_key.to_owned(),
+            name: if section.name.is_empty() { entry_key.to_owned() } else { section.name },
             title,
             description,
             ports,
+            port_labels: section.port_labels,
         })
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// ```
+    /// use ufw_auto_block::Application;
+    ///
+    /// let path = std::env::temp_dir().join("ufw-parser-doc-openssh");
+    /// std::fs::write(&path, "[OpenSSH]\ntitle=Secure shell server\ndescription=OpenSSH\nports=22/tcp\n").unwrap();
+    ///
+    /// let application = Application::parse_file(&path).unwrap();
+    /// let entry = application.entries()[0].as_ref().unwrap();
+    /// assert_eq!(entry.title(), "Secure shell server");
+    /// ```
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn ports(&self) -> &[ParseResult<UfwPort>] {
+        &self.ports
+    }
+
+    pub fn port_labels(&self) -> &HashMap<u16, String> {
+        &self.port_labels
+    }
+
+    // the `ports` value as it would be written in a profile, ports which failed to parse are left out
+    pub fn ports_as_string(&self) -> String {
+        self.ports
+            .iter()
+            .flatten()
+            .map(UfwPort::to_string)
+            .collect::<Vec<String>>()
+            .join("|")
+    }
+
+    // what `ufw allow <name>` expands to
+    pub fn to_rules(&self, action: RuleType, direction: RuleDirection) -> Vec<RuleEntry> {
+        self.ports
+            .iter()
+            .flatten()
+            .flat_map(|port| {
+                port.protocols
+                    .iter()
+                    .flatten()
+                    .map(move |proto| RuleEntry {
+                        destination_port: Some(port.number),
+                        destination_end_port: port.end_number,
+                        proto: proto.clone(),
+                        ..RuleEntry::new(action, direction)
+                    })
+            })
+            .collect()
+    }
+
     fn parse_ports(entry: &str) -> Vec<ParseResult<UfwPort>> {
         let mut ports_protocol = entry.split("/");
         let s = ports_protocol.next();
@@ -54,6 +122,7 @@ impl ApplicationEntry {
             return vec![Err(ParseError::PortsSectionEmpty)];
         }
 
+        // single ports and ranges (`8000:8100`) alike get both tcp and udp without an explicit protocol
         let protocols = match ports_protocol.next() {
             None => {
                 vec![Ok(Protocol::TCP), Ok(Protocol::UDP)]
@@ -79,27 +148,30 @@ impl ApplicationEntry {
                             .next()
                             .ok_or(ParseError::InvalidPortRange("number after colon hasn't been specified".to_string()))?;
 
+                        let number: u16 = start
+                            .parse()
+                            .map_err(|x: ParseIntError|
+                                ParseError::PortNotANumber(format!("Cannot parse first number in range: {}", x.to_string()))
+                            )?;
+                        let end_number: u16 = end
+                            .parse()
+                            .map_err(|x: ParseIntError|
+                                ParseError::PortNotANumber(format!("Cannot parse second number in range: {}", x.to_string()))
+                            )?;
+                        // like ufw, a range has to span at least two ports (`80:80` is rejected as well)
+                        if end_number <= number {
+                            Err(ParseError::InvalidPortRange(format!("{}:{}", number, end_number)))?
+                        }
+
                         UfwPort {
-                            number: start
-                                .parse()
-                                .map_err(|x: ParseIntError|
-                                    ParseError::PortNotANumber(format!("Cannot parse first number in range: {}", x.to_string()))
-                                )?,
-                            end_number: Some(end
-                                .parse()
-                                .map_err(|x: ParseIntError|
-                                    ParseError::PortNotANumber(format!("Cannot parse second number in range: {}", x.to_string()))
-                                )?),
+                            number,
+                            end_number: Some(end_number),
                             protocols: protocols.to_vec(),
                         }
                     }
                     false => {
                         UfwPort {
-                            number: p
-                                .parse()
-                                .map_err(|x: ParseIntError|
-                                    ParseError::PortNotANumber(x.to_string())
-                                )?,
+                            number: p.parse()?,
                             end_number: None,
                             protocols: protocols.to_vec(),
                         }
@@ -109,6 +181,42 @@ impl ApplicationEntry {
     }
 }
 
+// `ports=80/tcp # web`, the ini parser already drops these when reading files
+// but values which don't come from there might still carry them
+fn strip_comment(value: &str) -> &str {
+    value.split('#').next().unwrap_or(value).trim()
+}
+
+// what the ini parser doesn't keep of a section: the case of its name and its comments
+#[derive(Default)]
+struct RawSection {
+    name: String,
+    port_labels: HashMap<u16, String>,
+}
+
+// the sections of the raw file, keyed by their lowercased name like the ones of the ini parser
+fn parse_raw_sections(content: &str) -> HashMap<String, RawSection> {
+    let mut sections: HashMap<String, RawSection> = HashMap::new();
+    let mut section = String::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') && line.ends_with(']') {
+            let name = line[1..line.len() - 1].trim();
+            section = name.to_lowercase();
+            sections.entry(section.clone()).or_default().name = name.to_string();
+        } else if let Some(label) = line.strip_prefix('#').map(str::trim).and_then(|l| l.strip_prefix("label:")) {
+            let mut parts = label.trim().splitn(2, char::is_whitespace);
+            let port = parts.next().and_then(|port| port.parse::<u16>().ok());
+            let text = parts.next().map(str::trim).filter(|text| !text.is_empty());
+            if let (Some(port), Some(text)) = (port, text) {
+                sections.entry(section.clone()).or_default().port_labels.insert(port, text.to_string());
+            }
+        }
+    }
+
+    sections
+}
+
 #[derive(Debug)]
 pub struct Application {
     pub(crate) filepath: PathBuf,
@@ -122,18 +230,143 @@ impl Application {
             return Err(ParseError::FileNotFound);
         }
         let map = ini!(&inipath);
+        let mut sections = fs::read_to_string(&inipath)
+            .map(|content| parse_raw_sections(&content))
+            .unwrap_or_default();
 
-        let entries = map
+        let mut entries: Vec<ParseResult<ApplicationEntry>> = map
             .iter()
             .map(|(k, v)|
-                ApplicationEntry::parse(k, v)
+                ApplicationEntry::parse(Path::new(&inipath), k, v, sections.remove(k).unwrap_or_default())
             ).collect();
+        // the ini parser doesn't keep the order of the sections, sort them by name (errors last)
+        entries.sort_by(|a, b| match (a, b) {
+            (Ok(a), Ok(b)) => a.name.cmp(&b.name),
+            (a, b) => a.is_err().cmp(&b.is_err()),
+        });
 
         Ok(Application {
             filepath: path.into(),
             entries,
         })
     }
+
+    // fails on the first entry or port which doesn't parse instead of keeping it as an `Err`
+    pub fn parse_file_strict<P: Into<PathBuf> + Clone>(path: P) -> ParseResult<Application> {
+        let application = Application::parse_file(path)?;
+        application.first_error()?;
+
+        Ok(application)
+    }
+
+    fn first_error(&self) -> ParseResult<()> {
+        for entry in &self.entries {
+            let entry = entry.as_ref().map_err(Clone::clone)?;
+            if let Some(Err(err)) = entry.ports.iter().find(|port| port.is_err()) {
+                Err(err.clone())?
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn filepath(&self) -> &Path {
+        &self.filepath
+    }
+
+    pub fn entries(&self) -> &[ParseResult<ApplicationEntry>] {
+        &self.entries
+    }
+
+    pub fn to_ini_string(&self) -> ParseResult<String> {
+        // `ports_as_string` leaves out the ports which failed to parse, with none of them it's lossless
+        self.first_error()?;
+        let mut sections = vec![];
+
+        for entry in self.entries.iter().flatten() {
+            let mut lines = vec![
+                format!("[{}]", entry.name),
+                format!("title={}", entry.title),
+                format!("description={}", entry.description),
+                format!("ports={}", entry.ports_as_string()),
+            ];
+            let mut labels: Vec<(&u16, &String)> = entry.port_labels.iter().collect();
+            labels.sort();
+            lines.extend(labels.into_iter().map(|(port, label)| format!("# label: {} {}", port, label)));
+
+            sections.push(lines.join("\n"));
+        }
+
+        Ok(sections.join("\n\n") + "\n")
+    }
+}
+
+// `ufw app list` prints the profile names indented below `Available applications:`
+pub fn parse_app_list(text: &str) -> Vec<String> {
+    text.lines()
+        .skip_while(|line| !line.starts_with("Available applications:"))
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// `ufw app info <name>` prints `Profile:`, `Title:` and `Description:` (the latter wrapped over several lines)
+// followed by a `Port:` or `Ports:` block with one group per line
+pub fn parse_app_info(text: &str) -> ParseResult<ApplicationEntry> {
+    let mut fields: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut current = None;
+
+    for line in text.lines() {
+        match line.split_once(':') {
+            Some((key, value)) if !line.starts_with(char::is_whitespace) => {
+                let key = match key {
+                    "Port" => "Ports",
+                    key => key,
+                };
+                current = Some(key);
+                fields.entry(key).or_default().extend(Some(value.trim()).filter(|value| !value.is_empty()));
+            }
+            _ if line.trim().is_empty() => current = None,
+            _ => if let Some(key) = current {
+                fields.entry(key).or_default().push(line.trim());
+            }
+        }
+    }
+
+    let name = fields.get("Profile").map(|name| name.join(" ")).ok_or_else(|| ParseError::InvalidAppInfo(text.to_string()))?;
+    let title = fields.get("Title").map(|title| title.join(" ")).ok_or(ParseError::MissingTitle)?;
+    let description = fields.get("Description").map(|description| description.join(" ")).ok_or(ParseError::MissingDescription)?;
+    let groups = fields.get("Ports").ok_or(ParseError::MissingPorts)?;
+    let ports: Vec<ParseResult<UfwPort>> = groups
+        .iter()
+        .flat_map(|group| ApplicationEntry::parse_ports(group))
+        .collect();
+    if ports.is_empty() {
+        Err(ParseError::EmptyPortsSection)?
+    }
+
+    Ok(ApplicationEntry {
+        name,
+        title,
+        description,
+        ports,
+        port_labels: HashMap::new(),
+    })
+}
+
+// dotfiles, editor backups and the copies package managers leave behind on upgrades aren't profiles
+fn is_profile(path: &Path) -> bool {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+
+    !name.starts_with('.')
+        && ![".bak", "~", ".swp", ".dpkg-old", ".dpkg-new", ".dpkg-dist", ".rpmnew", ".rpmsave"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
 }
 
 pub fn parse_applications<P: Into<PathBuf>>(applications_directory: Option<P>) -> io::Result<Vec<ParseResult<Application>>> {
@@ -141,20 +374,305 @@ pub fn parse_applications<P: Into<PathBuf>>(applications_directory: Option<P>) -
 
     match fs::read_dir(path) {
         Ok(iter) => {
-            iter
+            // like ufw, read the profiles in the order of their file names
+            let mut files: Vec<io::Result<PathBuf>> = iter.map(|file| file.map(|file| file.path())).collect();
+            files.sort_by(|a, b| match (a, b) {
+                (Ok(a), Ok(b)) => a.cmp(b),
+                (a, b) => a.is_err().cmp(&b.is_err()),
+            });
+
+            let mut applications: Vec<ParseResult<Application>> = files
+                .into_iter()
+                // subdirectories (and anything else that isn't a regular file) aren't profiles, skip them.
+                // entries which can't be inspected (e.g. dangling symlinks) are reported instead
                 .filter_map(|file| {
-                    let file = file.ok()?;
-                    if file
-                        .file_type().ok()?
-                        .is_file() {
-                        Some(Ok(Application::parse_file(file.path())))
-                    } else {
-                        None
+                    let path = match file {
+                        Ok(path) => path,
+                        Err(err) => return Some(Err(ParseError::IOError(err.to_string()))),
+                    };
+                    if !is_profile(&path) {
+                        return None;
+                    }
+                    match fs::metadata(&path) {
+                        Ok(metadata) if metadata.is_file() => Some(Application::parse_file(path)),
+                        Ok(_) => None,
+                        Err(err) => Some(Err(ParseError::IOError(format!("{}: {}", path.display(), err)))),
                     }
-                }).collect()
+                }).collect();
+
+            // a profile defined in several files is taken from the last one, ufw does the same
+            // (`Duplicate profile '...', using last found`)
+            let mut seen = HashSet::new();
+            for application in applications.iter_mut().rev().flatten() {
+                application.entries.retain(|entry| match entry {
+                    Ok(entry) => seen.insert(entry.name.clone()),
+                    Err(_) => true,
+                });
+            }
+
+            Ok(applications)
         }
         Err(err) => {
             Ok(vec![Err(ParseError::IOError(err.to_string()))])
         }
     }
 }
+
+// the profiles of all files in one list sorted by their name, files which failed to parse are kept as errors (last)
+pub fn parse_profiles<P: Into<PathBuf>>(applications_directory: Option<P>) -> io::Result<Vec<ParseResult<ApplicationEntry>>> {
+    let mut entries: Vec<ParseResult<ApplicationEntry>> = parse_applications(applications_directory)?
+        .into_iter()
+        .flat_map(|application| match application {
+            Ok(application) => application.entries,
+            Err(err) => vec![Err(err)],
+        })
+        .collect();
+    entries.sort_by(|a, b| match (a, b) {
+        (Ok(a), Ok(b)) => a.name.cmp(&b.name),
+        (a, b) => a.is_err().cmp(&b.is_err()),
+    });
+
+    Ok(entries)
+}
+
+// every port opened by the given profiles next to the name of the entry it belongs to,
+// entries and ports which failed to parse are skipped
+pub fn collect_ports(applications: &[Application]) -> Vec<(String, UfwPort)> {
+    applications
+        .iter()
+        .flat_map(|application| application.entries.iter().flatten())
+        .flat_map(|entry| {
+            entry.ports
+                .iter()
+                .flatten()
+                .map(move |port| (entry.name.clone(), port.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("ufw-parser-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&directory).unwrap();
+
+        directory
+    }
+
+    fn profile(name: &str, content: &str) -> PathBuf {
+        let path = directory(name).join("profile");
+        fs::write(&path, content).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn parse_profiles_sorts_by_name_and_keeps_the_last_duplicate() {
+        let directory = directory("profiles");
+        let section = |name: &str, ports: &str| format!("[{}]\ntitle={}\ndescription={}\nports={}\n\n", name, name, name, ports);
+        fs::write(directory.join("a"), section("Zebra", "1/tcp") + &section("OpenSSH", "22/tcp")).unwrap();
+        fs::write(directory.join("b"), section("Apache", "80/tcp") + &section("OpenSSH", "2222/tcp")).unwrap();
+        fs::write(directory.join("c"), "[Broken]\ntitle=broken\n").unwrap();
+
+        let profiles = parse_profiles(Some(&directory)).unwrap();
+        let names: Vec<&str> = profiles.iter().flatten().map(ApplicationEntry::name).collect();
+        assert_eq!(names, vec!["Apache", "OpenSSH", "Zebra"]);
+        assert_eq!(profiles[1].as_ref().unwrap().ports_as_string(), "2222/tcp");
+        assert!(profiles.last().unwrap().is_err());
+    }
+
+    #[test]
+    fn to_ini_string_keeps_section_names_and_ports() {
+        let content = "[OpenSSH]\ntitle=Secure shell server\ndescription=OpenSSH is a free implementation of SSH\nports=22/tcp\n\n\
+            [Nginx Full]\ntitle=Web Server\ndescription=Small, but powerful web server\nports=80,443/tcp\n# label: 443 HTTPS\n";
+        let application = Application::parse_file(profile("to-ini", content)).unwrap();
+
+        assert_eq!(application.to_ini_string().unwrap(), "[Nginx Full]\ntitle=Web Server\ndescription=Small, but powerful web server\n\
+            ports=80/tcp|443/tcp\n# label: 443 HTTPS\n\n\
+            [OpenSSH]\ntitle=Secure shell server\ndescription=OpenSSH is a free implementation of SSH\nports=22/tcp\n");
+
+        let written = Application::parse_file(profile("to-ini-written", &application.to_ini_string().unwrap())).unwrap();
+        assert_eq!(written.to_ini_string().unwrap(), application.to_ini_string().unwrap());
+    }
+
+    #[test]
+    fn to_rules_yields_a_rule_per_port_and_protocol() {
+        let application = Application::parse_file(profile("to-rules", "[Mail]\ntitle=Mail\ndescription=Mail\nports=25/tcp|53\n")).unwrap();
+        let entry = application.entries()[0].as_ref().unwrap();
+
+        let rules = entry.to_rules(RuleType::ALLOW, RuleDirection::IN);
+        let ports: Vec<(Option<u16>, &Protocol)> = rules.iter().map(|rule| (rule.destination_port, &rule.proto)).collect();
+        assert_eq!(ports, vec![(Some(25), &Protocol::TCP), (Some(53), &Protocol::TCP), (Some(53), &Protocol::UDP)]);
+        assert!(rules.iter().all(|rule| rule.action.typ() == &RuleType::ALLOW && rule.action.direction() == &RuleDirection::IN));
+    }
+
+    #[test]
+    fn strips_inline_comments_from_ports() {
+        assert_eq!(strip_comment("80/tcp # web"), "80/tcp");
+
+        let entry = parse_app_info("Profile: Web\nTitle: Web\nDescription: Web\n\nPort:\n  80/tcp\n").unwrap();
+        let parsed = ApplicationEntry::parse(Path::new("web"), &"web".to_string(), &[
+            ("title".to_string(), Some("Web".to_string())),
+            ("description".to_string(), Some("Web".to_string())),
+            ("ports".to_string(), Some("80/tcp # web".to_string())),
+        ].iter().cloned().collect(), RawSection::default()).unwrap();
+        assert_eq!(parsed.ports_as_string(), "80/tcp");
+        assert_eq!(parsed.ports_as_string(), entry.ports_as_string());
+    }
+
+    #[test]
+    fn parse_applications_skips_subdirectories() {
+        let empty = directory("empty-applications");
+        assert!(parse_applications(Some(&empty)).unwrap().is_empty());
+
+        let nested = directory("nested-applications");
+        fs::create_dir_all(nested.join("subdirectory")).unwrap();
+        fs::write(nested.join("subdirectory/openssh"), "[OpenSSH]\ntitle=ssh\ndescription=ssh\nports=22/tcp\n").unwrap();
+        assert!(parse_applications(Some(&nested)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parses_port_labels_of_their_section() {
+        let content = "[Web]\ntitle=Web\ndescription=Web\nports=80,443/tcp\n# label: 80 HTTP\n# label: 443 HTTPS\n# label: nope\n\n\
+            [Mail]\ntitle=Mail\ndescription=Mail\nports=25/tcp\n";
+        let application = Application::parse_file(profile("labels", content)).unwrap();
+        let entry = |name: &str| application.entries().iter().flatten().find(|entry| entry.name() == name).unwrap();
+
+        let labels = entry("Web").port_labels();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[&80], "HTTP");
+        assert_eq!(labels[&443], "HTTPS");
+        assert!(entry("Mail").port_labels().is_empty());
+    }
+
+    #[test]
+    fn parse_ports_gives_a_range_without_protocol_both_protocols() {
+        let ports = ApplicationEntry::parse_ports("8000:8100");
+        assert_eq!(ports.len(), 1);
+
+        let port = ports[0].as_ref().unwrap();
+        assert_eq!((port.number, port.end_number), (8000, Some(8100)));
+        let protocols: Vec<&Protocol> = port.protocols.iter().flatten().collect();
+        assert_eq!(protocols, vec![&Protocol::TCP, &Protocol::UDP]);
+        assert_eq!(port.to_string(), "8000:8100");
+    }
+
+    #[test]
+    fn ports_as_string_round_trips_mixed_ports() {
+        let ports = "22/tcp|53|8000:8100/udp|60000:61000";
+        let application = Application::parse_file(profile("ports-as-string", &format!("[Mixed]\ntitle=Mixed\ndescription=Mixed\nports={}\n", ports))).unwrap();
+        let entry = application.entries()[0].as_ref().unwrap();
+
+        assert_eq!(entry.ports_as_string(), ports);
+    }
+
+    #[test]
+    fn parse_applications_reports_dangling_symlinks_and_follows_valid_ones() {
+        let directory = directory("symlinked-applications");
+        let profile = "[OpenSSH]\ntitle=ssh\ndescription=ssh\nports=22/tcp\n";
+        fs::write(directory.join("a-openssh"), profile).unwrap();
+        fs::create_dir_all(directory.join("b-subdirectory")).unwrap();
+        let _ = fs::remove_file(directory.join("c-dangling"));
+        std::os::unix::fs::symlink(directory.join("missing"), directory.join("c-dangling")).unwrap();
+        let target = self::directory("symlink-target").join("web");
+        fs::write(&target, "[Web]\ntitle=web\ndescription=web\nports=80/tcp\n").unwrap();
+        let _ = fs::remove_file(directory.join("d-web"));
+        std::os::unix::fs::symlink(&target, directory.join("d-web")).unwrap();
+
+        let applications = parse_applications(Some(&directory)).unwrap();
+        assert_eq!(applications.len(), 3);
+        assert_eq!(applications[0].as_ref().unwrap().entries()[0].as_ref().unwrap().name(), "OpenSSH");
+        assert!(matches!(&applications[1], Err(ParseError::IOError(message)) if message.contains("c-dangling")));
+        assert_eq!(applications[2].as_ref().unwrap().entries()[0].as_ref().unwrap().name(), "Web");
+    }
+
+    #[test]
+    fn parse_ports_rejects_reversed_and_empty_ranges() {
+        let port = ApplicationEntry::parse_ports("80:443/tcp").remove(0).unwrap();
+        assert_eq!((port.number, port.end_number), (80, Some(443)));
+
+        assert!(matches!(&ApplicationEntry::parse_ports("443:80/tcp")[0], Err(ParseError::InvalidPortRange(range)) if range == "443:80"));
+        // ufw requires a range to span at least two ports
+        assert!(matches!(&ApplicationEntry::parse_ports("80:80")[0], Err(ParseError::InvalidPortRange(range)) if range == "80:80"));
+    }
+
+    #[test]
+    fn errors_name_the_file_and_section() {
+        let path = profile("in-file", "[openssh-server]\ntitle=ssh\ndescription=ssh\nports=|\n");
+        let application = Application::parse_file(&path).unwrap();
+
+        let error = application.entries()[0].as_ref().unwrap_err();
+        assert!(matches!(error, ParseError::InFile { source, .. } if matches!(**source, ParseError::EmptyPortsSection)));
+        assert_eq!(error.to_string(), format!("openssh-server: ports section doesn't contain any port in {}", path.display()));
+    }
+
+    #[test]
+    fn collect_ports_flattens_every_profile() {
+        let web = Application::parse_file(profile("collect-web", "[Web]\ntitle=Web\ndescription=Web\nports=80,443/tcp\n\n\
+            [Broken]\ntitle=Broken\n")).unwrap();
+        let games = Application::parse_file(profile("collect-games", "[Games]\ntitle=Games\ndescription=Games\nports=27000:27015/udp|foo\n")).unwrap();
+
+        let ports = collect_ports(&[web, games]);
+        let rendered: Vec<(&str, String)> = ports.iter().map(|(name, port)| (name.as_str(), port.to_string())).collect();
+        assert_eq!(rendered, vec![("Web", "80/tcp".to_string()), ("Web", "443/tcp".to_string()), ("Games", "27000:27015/udp".to_string())]);
+    }
+
+    #[test]
+    fn ports_groups_are_separated_by_pipes_or_whitespace() {
+        let ports = |value: &str| {
+            let values: HashMap<String, Option<String>> = [
+                ("title".to_string(), Some("App".to_string())),
+                ("description".to_string(), Some("App".to_string())),
+                ("ports".to_string(), Some(value.to_string())),
+            ].iter().cloned().collect();
+            let entry = ApplicationEntry::parse(Path::new("app"), &"app".to_string(), &values, RawSection::default()).unwrap();
+
+            entry.ports().iter().map(|port| port.as_ref().unwrap().to_string()).collect::<Vec<String>>()
+        };
+
+        assert_eq!(ports("80|443"), vec!["80", "443"]);
+        assert_eq!(ports("80 443"), vec!["80", "443"]);
+        assert_eq!(ports("80,443/tcp|53/udp"), vec!["80/tcp", "443/tcp", "53/udp"]);
+        assert_eq!(ports("80,443/tcp 53/udp"), ports("80,443/tcp|53/udp"));
+    }
+
+    #[test]
+    fn parse_applications_skips_backups_and_dotfiles() {
+        let directory = directory("backup-applications");
+        let profile = "[OpenSSH]\ntitle=ssh\ndescription=ssh\nports=22/tcp\n";
+        fs::write(directory.join("openssh"), profile).unwrap();
+        for backup in ["openssh.bak", "openssh~", ".openssh.swp", "openssh.dpkg-old"].iter() {
+            fs::write(directory.join(backup), "not an ini file [").unwrap();
+        }
+
+        let applications = parse_applications(Some(&directory)).unwrap();
+        assert_eq!(applications.len(), 1);
+        assert!(applications[0].as_ref().unwrap().filepath().ends_with("openssh"));
+    }
+
+    #[test]
+    fn to_rules_renders_port_ranges() {
+        let application = Application::parse_file(profile("to-rules-ranges", "[Mosh]\ntitle=Mosh\ndescription=Mosh\nports=22/tcp|60000:61000/udp\n")).unwrap();
+        let entry = application.entries()[0].as_ref().unwrap();
+
+        let rules: Vec<String> = entry.to_rules(RuleType::ALLOW, RuleDirection::IN).iter().map(RuleEntry::to_string).collect();
+        assert_eq!(rules, vec!["allow in proto tcp to any port 22", "allow in proto udp to any port 60000:61000"]);
+    }
+
+    #[test]
+    fn strict_parsing_fails_on_the_first_bad_port() {
+        let path = profile("strict", "[Web]\ntitle=Web\ndescription=Web\nports=80/tcp|http|443/tcp\n");
+
+        let lenient = Application::parse_file(&path).unwrap();
+        let ports = lenient.entries()[0].as_ref().unwrap().ports();
+        assert_eq!(ports.len(), 3);
+        assert!(ports[1].is_err() && ports[0].is_ok() && ports[2].is_ok());
+
+        assert!(matches!(Application::parse_file_strict(&path), Err(ParseError::InFile { source, .. }) if matches!(*source, ParseError::PortNotANumber(_))));
+        let missing = profile("strict-missing", "[Web]\ntitle=Web\nports=80/tcp\n");
+        assert!(Application::parse_file_strict(&missing).is_err());
+        assert!(Application::parse_file_strict(profile("strict-valid", "[Web]\ntitle=Web\ndescription=Web\nports=80/tcp\n")).is_ok());
+    }
+}
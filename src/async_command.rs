@@ -0,0 +1,113 @@
+use std::io;
+use std::path::PathBuf;
+use std::process::Output;
+
+use anyhow::Result;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use crate::{CommandResult, Config, DefaultPolicies, Line, LoggingLevel, parse_defaults, parse_info, parse_version, UfwCommand};
+use crate::numbered;
+
+// ufw isn't safe to run concurrently, see the lock of `UfwCommand`
+static EXEC_LOCK: Mutex<()> = Mutex::const_new(());
+
+// `UfwCommand` running ufw through `tokio::process`, the configuration is the one of the sync command.
+// invocations are serialized through a `tokio::sync::Mutex` of their own, mixing them with concurrent
+// sync invocations isn't safe
+pub struct AsyncUfwCommand {
+    command: UfwCommand,
+}
+
+impl AsyncUfwCommand {
+    pub fn new() -> AsyncUfwCommand {
+        AsyncUfwCommand::from(UfwCommand::new())
+    }
+
+    pub fn from_config(config: &Config) -> AsyncUfwCommand {
+        AsyncUfwCommand::from(UfwCommand::from_config(config))
+    }
+
+    pub fn with_executable<P: Into<PathBuf>>(self, executable_path: P) -> AsyncUfwCommand {
+        AsyncUfwCommand::from(self.command.with_executable(executable_path))
+    }
+
+    pub fn dry_run(self, on: bool) -> AsyncUfwCommand {
+        AsyncUfwCommand::from(self.command.dry_run(on))
+    }
+
+    pub async fn version(&self) -> CommandResult<String> {
+        let text = self.stdout(vec!["version"]).await?;
+
+        Ok(parse_version(&text)?)
+    }
+
+    pub async fn info(&self) -> CommandResult<(bool, LoggingLevel)> {
+        let text = self.stdout(vec!["status", "verbose"]).await?;
+
+        Ok(parse_info(&text)?)
+    }
+
+    pub async fn defaults(&self) -> CommandResult<DefaultPolicies> {
+        let text = self.stdout(vec!["status", "verbose"]).await?;
+
+        Ok(parse_defaults(&text)?)
+    }
+
+    pub async fn status_numbered(&self) -> CommandResult<Vec<Result<Line>>> {
+        let text = self.stdout(vec!["status", "numbered"]).await?;
+
+        Ok(numbered::parse_status(&text))
+    }
+
+    async fn stdout(&self, args: Vec<&str>) -> CommandResult<String> {
+        let output = self.exec(args).await?;
+
+        if output.status.success() {
+            Ok(UfwCommand::parse_stdout(output.stdout)?)
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    async fn exec(&self, args: Vec<&str>) -> io::Result<Output> {
+        let _guard = EXEC_LOCK.lock().await;
+
+        Command::from(self.command.command(args)).output().await
+    }
+}
+
+impl Default for AsyncUfwCommand {
+    fn default() -> Self {
+        AsyncUfwCommand::new()
+    }
+}
+
+impl From<UfwCommand> for AsyncUfwCommand {
+    fn from(command: UfwCommand) -> Self {
+        AsyncUfwCommand {
+            command,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn runs_ufw_like_the_sync_command() {
+        let path = std::env::temp_dir().join(format!("ufw-parser-{}-async", std::process::id()));
+        fs::write(&path, "#!/bin/sh\necho \"ufw 0.36.1\"\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        let command = AsyncUfwCommand::new().with_executable(&path);
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build().unwrap();
+        runtime.block_on(async {
+            assert_eq!(command.version().await.unwrap(), UfwCommand::new().with_executable(&path).version().unwrap());
+        });
+    }
+}
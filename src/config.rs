@@ -1,12 +1,40 @@
-use std::fs::File;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::{self, File};
 use std::io::{self, Read};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use serde_derive::Deserialize;
 
+use crate::{ParseError, ParseResult};
+use crate::ufw::{LoggingLevel, RuleType};
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
     block_after_times: i32,
+    pub(crate) default_logging: Option<LoggingLevel>,
+    pub(crate) default_incoming: Option<RuleType>,
+    pub(crate) default_outgoing: Option<RuleType>,
+    pub(crate) ufw_executable: Option<PathBuf>,
+    pub(crate) sudo: Option<Sudo>,
+}
+
+// `sudo = true` or a custom prefix like `sudo = "doas"`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Sudo {
+    Enabled(bool),
+    Command(String),
+}
+
+impl Sudo {
+    pub(crate) fn prefix(&self) -> Vec<String> {
+        match self {
+            Sudo::Enabled(true) => vec!["sudo".to_string()],
+            Sudo::Enabled(false) => vec![],
+            Sudo::Command(command) => command.split_whitespace().map(String::from).collect(),
+        }
+    }
 }
 
 impl Config {
@@ -29,3 +57,267 @@ impl Config {
         }
     }
 }
+
+// /etc/default/ufw, a shell-style `KEY=value` file which is sourced by ufw's init scripts
+#[derive(Debug)]
+pub struct UfwDefaults {
+    values: HashMap<String, String>,
+}
+
+impl UfwDefaults {
+    pub fn from_file<T: AsRef<Path>>(filename: T) -> ParseResult<UfwDefaults> {
+        let filename = filename.as_ref();
+        if !filename.exists() {
+            return Err(ParseError::FileNotFound);
+        }
+        let content = fs::read_to_string(filename)?;
+
+        Ok(UfwDefaults::parse(content))
+    }
+
+    pub fn parse<S: ToString>(content: S) -> UfwDefaults {
+        let values = content
+            .to_string()
+            .lines()
+            .filter_map(parse_shell_variable)
+            .collect();
+
+        UfwDefaults {
+            values,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    fn require(&self, key: &str) -> ParseResult<&str> {
+        self.get(key).ok_or_else(|| ParseError::MissingSetting(key.to_string()))
+    }
+
+    fn flag(&self, key: &str) -> ParseResult<bool> {
+        match self.require(key)?.to_ascii_lowercase().as_str() {
+            "yes" => Ok(true),
+            "no" => Ok(false),
+            value => Err(ParseError::InvalidSetting(format!("{}={}", key, value))),
+        }
+    }
+
+    fn policy(&self, key: &str) -> ParseResult<RuleType> {
+        let value = self.require(key)?;
+
+        parse_policy(value).ok_or_else(|| ParseError::InvalidSetting(format!("{}={}", key, value)))
+    }
+
+    pub fn ipv6_enabled(&self) -> ParseResult<bool> {
+        self.flag("IPV6")
+    }
+
+    pub fn manage_builtins(&self) -> ParseResult<bool> {
+        self.flag("MANAGE_BUILTINS")
+    }
+
+    pub fn default_input_policy(&self) -> ParseResult<RuleType> {
+        self.policy("DEFAULT_INPUT_POLICY")
+    }
+
+    pub fn default_output_policy(&self) -> ParseResult<RuleType> {
+        self.policy("DEFAULT_OUTPUT_POLICY")
+    }
+
+    pub fn default_forward_policy(&self) -> ParseResult<RuleType> {
+        self.policy("DEFAULT_FORWARD_POLICY")
+    }
+
+    // `SKIP` means that profiles which get updated aren't touched, there is no RuleType for that
+    pub fn default_application_policy(&self) -> ParseResult<Option<RuleType>> {
+        match self.require("DEFAULT_APPLICATION_POLICY")? {
+            "SKIP" => Ok(None),
+            _ => self.policy("DEFAULT_APPLICATION_POLICY").map(Some),
+        }
+    }
+
+    pub fn ipt_sysctl(&self) -> Option<&str> {
+        self.get("IPT_SYSCTL")
+    }
+
+    pub fn ipt_modules(&self) -> Vec<&str> {
+        self.get("IPT_MODULES")
+            .map(|modules| modules.split_whitespace().collect())
+            .unwrap_or_default()
+    }
+}
+
+// /etc/ufw/ufw.conf, decides whether ufw is started at boot and with which logging level.
+// a type of its own since `Config` is this crate's TOML configuration, which shares no keys with ufw.conf
+#[derive(Debug)]
+pub struct UfwConf {
+    pub(crate) enabled: bool,
+    pub(crate) loglevel: LoggingLevel,
+    // the file as read, `to_ufw_conf_string` only replaces the lines of known keys
+    lines: Vec<String>,
+}
+
+impl UfwConf {
+    pub fn from_file<T: AsRef<Path>>(filename: T) -> ParseResult<UfwConf> {
+        let filename = filename.as_ref();
+        if !filename.exists() {
+            return Err(ParseError::FileNotFound);
+        }
+        let content = fs::read_to_string(filename)?;
+
+        UfwConf::parse(content)
+    }
+
+    pub fn parse<S: ToString>(content: S) -> ParseResult<UfwConf> {
+        let content = content.to_string();
+        let defaults = UfwDefaults::parse(&content);
+
+        let enabled = defaults.flag("ENABLED")?;
+        let loglevel = match defaults.require("LOGLEVEL")? {
+            // `ufw logging on` without a level logs at `low`
+            "on" => LoggingLevel::Low,
+            "off" => LoggingLevel::Off,
+            level => LoggingLevel::try_from(("on", level))?,
+        };
+
+        Ok(UfwConf {
+            enabled,
+            loglevel,
+            lines: content.lines().map(String::from).collect(),
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn loglevel(&self) -> LoggingLevel {
+        self.loglevel
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) -> &mut UfwConf {
+        self.enabled = enabled;
+
+        self
+    }
+
+    pub fn set_loglevel(&mut self, loglevel: LoggingLevel) -> &mut UfwConf {
+        self.loglevel = loglevel;
+
+        self
+    }
+
+    pub fn to_ufw_conf_string(&self) -> String {
+        // like the shell the last assignment wins, so that's the one that's replaced and duplicates are kept as they are
+        let last = |name: &str| self.lines
+            .iter()
+            .rposition(|line| matches!(parse_shell_variable(line), Some((key, _)) if key == name));
+        let (enabled, loglevel) = (last("ENABLED"), last("LOGLEVEL"));
+
+        let mut lines = self.lines.clone();
+        let mut set = |index: Option<usize>, line: String| match index {
+            Some(index) => lines[index] = line,
+            None => lines.push(line),
+        };
+        set(enabled, format!("ENABLED={}", if self.enabled { "yes" } else { "no" }));
+        set(loglevel, format!("LOGLEVEL={}", self.loglevel));
+
+        lines.join("\n") + "\n"
+    }
+}
+
+// the file uses iptables targets instead of ufw's own names
+fn parse_policy(value: &str) -> Option<RuleType> {
+    match value.to_ascii_uppercase().as_str() {
+        "ACCEPT" => Some(RuleType::ALLOW),
+        "DROP" => Some(RuleType::DENY),
+        "REJECT" => Some(RuleType::REJECT),
+        _ => RuleType::try_from(value).ok(),
+    }
+}
+
+pub(crate) fn parse_shell_variable(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (key, value) = line.split_once('=')?;
+    let value = value.trim();
+    let value = match value.chars().next() {
+        Some(quote) if quote == '"' || quote == '\'' => {
+            let value = &value[1..];
+            value.find(quote).map(|end| &value[..end]).unwrap_or(value)
+        }
+        // unquoted values end at the first whitespace, everything after it is a comment
+        _ => value.split_whitespace().next().unwrap_or(""),
+    };
+
+    Some((key.trim().to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULTS: &str = "# /etc/default/ufw\n#\n\n# Set to yes to apply rules to support IPv6\nIPV6=yes\n\n\
+        DEFAULT_INPUT_POLICY=\"DROP\"\nDEFAULT_OUTPUT_POLICY=\"ACCEPT\"\nDEFAULT_FORWARD_POLICY=\"REJECT\"\n\
+        DEFAULT_APPLICATION_POLICY=\"SKIP\"\nMANAGE_BUILTINS=no # comment\n\
+        IPT_SYSCTL=/etc/ufw/sysctl.conf\nIPT_MODULES=\"nf_conntrack_ftp nf_nat_ftp\"\n";
+
+    #[test]
+    fn parses_the_defaults_file() {
+        let defaults = UfwDefaults::parse(DEFAULTS);
+
+        assert!(defaults.ipv6_enabled().unwrap());
+        assert!(!defaults.manage_builtins().unwrap());
+        assert!(matches!(defaults.default_input_policy(), Ok(RuleType::DENY)));
+        assert!(matches!(defaults.default_output_policy(), Ok(RuleType::ALLOW)));
+        assert!(matches!(defaults.default_forward_policy(), Ok(RuleType::REJECT)));
+        assert!(matches!(defaults.default_application_policy(), Ok(None)));
+        assert_eq!(defaults.ipt_sysctl(), Some("/etc/ufw/sysctl.conf"));
+        assert_eq!(defaults.ipt_modules(), vec!["nf_conntrack_ftp", "nf_nat_ftp"]);
+    }
+
+    #[test]
+    fn reports_missing_and_invalid_settings() {
+        let defaults = UfwDefaults::parse("IPV6=maybe\n");
+
+        assert!(matches!(defaults.ipv6_enabled(), Err(ParseError::InvalidSetting(setting)) if setting == "IPV6=maybe"));
+        assert!(matches!(defaults.manage_builtins(), Err(ParseError::MissingSetting(key)) if key == "MANAGE_BUILTINS"));
+    }
+
+    #[test]
+    fn ufw_conf_round_trips_and_keeps_unknown_keys() {
+        let content = "# /etc/ufw/ufw.conf\n#\n\n# Set to yes to start on boot. If setting this remotely, be sure to add a rule\n\
+            # to allow your remote connection before starting ufw. Eg: 'ufw allow 22/tcp'\nENABLED=\"no\"\n\n\
+            # Please use the 'ufw' command to set the loglevel. Eg: 'ufw logging medium'.\n\
+            # See 'man ufw' for details.\nLOGLEVEL='medium'\nCUSTOM=kept\n";
+        let mut conf = UfwConf::parse(content).unwrap();
+        assert!(!conf.enabled());
+        assert_eq!(conf.loglevel(), LoggingLevel::Medium);
+
+        assert_eq!(conf.to_ufw_conf_string(), content.replace("ENABLED=\"no\"", "ENABLED=no").replace("LOGLEVEL='medium'", "LOGLEVEL=medium"));
+
+        conf.set_enabled(true).set_loglevel(LoggingLevel::Off);
+        let written = conf.to_ufw_conf_string();
+        assert!(written.contains("\nENABLED=yes\n") && written.contains("\nLOGLEVEL=off\n") && written.ends_with("CUSTOM=kept\n"));
+        assert!(UfwConf::parse(&written).unwrap().enabled());
+
+        assert!(UfwConf::parse("ENABLED=yes\n").is_err());
+    }
+
+    #[test]
+    fn ufw_conf_keeps_duplicate_keys() {
+        let content = "ENABLED=no\nLOGLEVEL=low\n# edited by hand\nENABLED=yes\n";
+        let mut conf = UfwConf::parse(content).unwrap();
+        assert!(conf.enabled());
+
+        assert_eq!(conf.to_ufw_conf_string(), content);
+
+        conf.set_enabled(false);
+        assert_eq!(conf.to_ufw_conf_string(), "ENABLED=no\nLOGLEVEL=low\n# edited by hand\nENABLED=no\n");
+        assert!(!UfwConf::parse(conf.to_ufw_conf_string()).unwrap().enabled());
+    }
+}
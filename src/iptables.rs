@@ -0,0 +1,134 @@
+use crate::Protocol;
+use crate::ufw::{IpVersion, RateLimit, RuleDirection, RuleEntry, RuleType};
+
+impl RuleEntry {
+    // ufw keeps its rules in `ufw-user-input`, `ufw-user-output` and `ufw-user-forward`, `ufw6-...` for ip6tables
+    fn iptables_chain_prefix(&self) -> &str {
+        let v6 = self.ip_version == Some(IpVersion::V6)
+            || [&self.source_address, &self.destination_address].iter().any(|address| address.is_some_and(|address| address.addr().is_ipv6()));
+
+        if v6 { "ufw6" } else { "ufw" }
+    }
+
+    fn iptables_matches(&self, proto: Option<&Protocol>) -> Vec<String> {
+        let mut matches = vec![];
+
+        if let Some(interface) = &self.interface {
+            let flag = if self.action.direction() == &RuleDirection::OUT { "-o" } else { "-i" };
+            matches.extend(vec![flag.to_string(), interface.to_owned()]);
+        }
+        if let Some(interface) = &self.out_interface {
+            matches.extend(vec!["-o".to_string(), interface.to_owned()]);
+        }
+        if let Some(proto) = proto {
+            matches.extend(vec!["-p".to_string(), proto.to_string()]);
+        }
+        if let Some(address) = &self.source_address {
+            matches.extend(vec!["-s".to_string(), address.to_string()]);
+        }
+        // ufw matches ranges through the multiport module
+        let port_matches = |port: u16, end_port: Option<u16>, flag: &str| match end_port {
+            Some(end_port) => vec!["-m".to_string(), "multiport".to_string(), format!("--{}s", flag), format!("{}:{}", port, end_port)],
+            None => vec![format!("--{}", flag), port.to_string()],
+        };
+
+        if let Some(port) = self.source_port {
+            matches.extend(port_matches(port, self.source_end_port, "sport"));
+        }
+        if let Some(address) = &self.destination_address {
+            matches.extend(vec!["-d".to_string(), address.to_string()]);
+        }
+        if let Some(port) = self.destination_port {
+            matches.extend(port_matches(port, self.destination_end_port, "dport"));
+        }
+
+        matches
+    }
+
+    // the lines ufw itself would write into its user rules (see `ufw show user-rules`), one per line.
+    // iptables can only match ports for a specific protocol, so like ufw a port rule without one becomes a tcp and an udp rule
+    pub fn to_iptables(&self) -> String {
+        let prefix = self.iptables_chain_prefix();
+        let chain = match self.action.direction() {
+            RuleDirection::IN => format!("{}-user-input", prefix),
+            RuleDirection::OUT => format!("{}-user-output", prefix),
+            RuleDirection::FWD => format!("{}-user-forward", prefix),
+        };
+        let has_port = self.source_port.is_some() || self.destination_port.is_some();
+        let protocols = match &self.proto {
+            Protocol::ANY if has_port => vec![Some(&Protocol::TCP), Some(&Protocol::UDP)],
+            Protocol::ANY => vec![None],
+            proto => vec![Some(proto)],
+        };
+
+        protocols
+            .into_iter()
+            .flat_map(|proto| {
+                let rule = format!("-A {} {}", chain, self.iptables_matches(proto).join(" "));
+                let rule = rule.trim_end();
+
+                match self.action.typ() {
+                    RuleType::ALLOW => vec![format!("{} -j ACCEPT", rule)],
+                    RuleType::DENY => vec![format!("{} -j DROP", rule)],
+                    // ufw resets rejected tcp connections instead of answering with icmp
+                    RuleType::REJECT if proto == Some(&Protocol::TCP) => vec![format!("{} -j REJECT --reject-with tcp-reset", rule)],
+                    RuleType::REJECT => vec![format!("{} -j REJECT", rule)],
+                    RuleType::LIMIT => {
+                        let limit = RateLimit::default();
+                        vec![
+                            format!("{} -m conntrack --ctstate NEW -m recent --set", rule),
+                            format!("{} -m conntrack --ctstate NEW -m recent --update --seconds {} --hitcount {} -j {}-user-limit",
+                                    rule, limit.seconds(), limit.hits(), prefix),
+                            format!("{} -j {}-user-limit-accept", rule, prefix),
+                        ]
+                    }
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_rule_spec;
+
+    #[test]
+    fn tcp_port_rule() {
+        let rule = parse_rule_spec("allow 22/tcp").unwrap();
+
+        assert_eq!(rule.to_iptables(), "-A ufw-user-input -p tcp --dport 22 -j ACCEPT");
+    }
+
+    #[test]
+    fn cidr_source_deny() {
+        let rule = parse_rule_spec("deny from 10.0.0.0/8").unwrap();
+
+        assert_eq!(rule.to_iptables(), "-A ufw-user-input -s 10.0.0.0/8 -j DROP");
+    }
+
+    #[test]
+    fn any_protocol_port_rule_becomes_tcp_and_udp() {
+        let rule = parse_rule_spec("allow out 53").unwrap();
+
+        assert_eq!(rule.to_iptables(), "-A ufw-user-output -p tcp --dport 53 -j ACCEPT\n-A ufw-user-output -p udp --dport 53 -j ACCEPT");
+    }
+
+    #[test]
+    fn limit_and_reject_rules() {
+        let limit = parse_rule_spec("limit 22/tcp").unwrap();
+        assert_eq!(limit.to_iptables(), "-A ufw-user-input -p tcp --dport 22 -m conntrack --ctstate NEW -m recent --set\n\
+            -A ufw-user-input -p tcp --dport 22 -m conntrack --ctstate NEW -m recent --update --seconds 30 --hitcount 6 -j ufw-user-limit\n\
+            -A ufw-user-input -p tcp --dport 22 -j ufw-user-limit-accept");
+
+        let reject = parse_rule_spec("reject in on eth0 to 2001:db8::1 port 25 proto tcp").unwrap();
+        assert_eq!(reject.to_iptables(), "-A ufw6-user-input -i eth0 -p tcp -d 2001:db8::1 --dport 25 -j REJECT --reject-with tcp-reset");
+    }
+
+    #[test]
+    fn port_ranges_use_multiport() {
+        let rule = parse_rule_spec("allow from any port 1000:2000 to any port 6000:6007 proto udp").unwrap();
+
+        assert_eq!(rule.to_iptables(), "-A ufw-user-input -p udp -m multiport --sports 1000:2000 -m multiport --dports 6000:6007 -j ACCEPT");
+    }
+}
@@ -12,52 +12,216 @@ extern crate serde_derive;
 extern crate thiserror;
 extern crate toml;
 
+use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
 
 use thiserror::Error;
 
-pub use config::Config;
+pub use config::{Config, Sudo, UfwConf, UfwDefaults};
 pub use numbered::*;
-pub use ufw::UfwCommand;
+pub use protocol::Protocol;
+pub use ufw::{ApplyReport, DefaultPolicies, DefaultPolicy, IpVersion, LoggingLevel, parse_defaults, parse_info, parse_raw, parse_rule_spec, parse_version, parse_with_version, RateLimit, RawRule, ReportFormats, RuleDirection, RuleDirectionDefaults, RuleEntry, RuleEntryBuilder, RuleType, Ufw, UfwAction, UfwCommand, UfwPort};
+// `numbered` has its own `Address`
+pub use ufw::Address as RuleAddress;
 
-pub use crate::application::{Application, ApplicationEntry, parse_applications};
+pub use crate::application::{Application, ApplicationEntry, collect_ports, parse_app_info, parse_app_list, parse_applications, parse_profiles};
+#[cfg(feature = "async")]
+pub use crate::async_command::AsyncUfwCommand;
+#[cfg(feature = "notify")]
+pub use crate::watch::{UfwChange, watch_ufw_dir};
 
 mod config;
 mod ufw;
 mod application;
+#[cfg(feature = "async")]
+mod async_command;
+#[cfg(feature = "iptables")]
+mod iptables;
 mod numbered;
+mod protocol;
+#[cfg(feature = "notify")]
+mod watch;
 
 #[derive(Clone, Debug, Error)]
 pub enum ParseError {
-    #[error("port range must have a protocol attached")]
+    #[error("invalid port range: {0}")]
     InvalidPortRange(String),
-    #[error("port must be a number")]
+    #[error("port must be a number: {0}")]
     PortNotANumber(String),
     #[error("ufw ini file not found")]
     FileNotFound,
-    #[error("all port numbers must appear before the protocol")]
+    #[error("all port numbers must appear before the protocol: {0}")]
     NumberAfterProtocol(String),
-    #[error("not a valid protocol")]
+    #[error("not a valid protocol: {0}")]
     InvalidProtocol(String),
-    #[error("an IO error has occured")]
+    #[error("an IO error has occured: {0}")]
     IOError(String),
     #[error("ports section is empty")]
     PortsSectionEmpty,
-    #[error("")]
+    #[error("title is missing")]
     MissingTitle,
-    #[error("")]
+    #[error("description is missing")]
     MissingDescription,
-    #[error("")]
+    #[error("ports are missing")]
     MissingPorts,
-    #[error("")]
+    #[error("ports section doesn't contain any port")]
     EmptyPortsSection,
-    #[error("")]
+    #[error("invalid logging level: {0}")]
     InvalidLoggingLevel(String),
-    #[error("")]
+    #[error("invalid rule direction: {0}")]
     WrongRuleDirection(String),
-    #[error("")]
+    #[error("invalid rule type: {0}")]
     WrongRuleType(String),
-    #[error("")]
+    #[error("invalid default policies: {0}")]
     InvalidDefaults(String),
+    #[error("not a valid report format: {0}")]
+    InvalidReportFormat(String),
+    #[error("setting is missing: {0}")]
+    MissingSetting(String),
+    #[error("setting has an invalid value: {0}")]
+    InvalidSetting(String),
+    #[error("not a valid row of the ufw status table: {0}")]
+    InvalidStatusRow(String),
+    #[error("not a valid ufw rule: {0}")]
+    InvalidRuleSpec(String),
+    #[error("negated matches aren't supported: {0}")]
+    NegatedRule(String),
+    #[error("not a valid ufw version: {0}")]
+    InvalidVersion(String),
+    #[error("not a valid `ufw app info` output: {0}")]
+    InvalidAppInfo(String),
+    // errors of a section within an application profile
+    #[error("{section}: {source} in {}", path.display())]
+    InFile { path: PathBuf, section: String, source: Box<ParseError> },
+}
+
+impl ParseError {
+    pub(crate) fn in_file<P: AsRef<Path>, S: ToString>(self, path: P, section: S) -> ParseError {
+        ParseError::InFile { path: path.as_ref().to_path_buf(), section: section.to_string(), source: Box::new(self) }
+    }
+}
+
+impl From<std::io::Error> for ParseError {
+    fn from(err: std::io::Error) -> Self {
+        ParseError::IOError(err.to_string())
+    }
+}
+
+impl From<ParseIntError> for ParseError {
+    fn from(err: ParseIntError) -> Self {
+        ParseError::PortNotANumber(err.to_string())
+    }
 }
 
 pub type ParseResult<V> = Result<V, ParseError>;
+
+#[derive(Clone, Debug, Error)]
+pub enum CommandError {
+    #[error("couldn't execute ufw: {0}")]
+    Spawn(String),
+    // `code` is `None` when ufw was terminated by a signal
+    #[error("ufw execution unsuccessful ({}): {stderr}", code.map_or("no exit code".to_string(), |code| format!("exit code {}", code)))]
+    Failed { code: Option<i32>, stderr: String },
+    #[error("ufw requires root; run with sudo")]
+    RootRequired,
+    // `applied` are the indices of the rules which were submitted before `failed`
+    #[error("submitting rule {failed} failed after applying {applied:?}: {source}")]
+    PartialSubmit { applied: Vec<usize>, failed: usize, source: Box<CommandError> },
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(err: std::io::Error) -> Self {
+        CommandError::Spawn(err.to_string())
+    }
+}
+
+pub type CommandResult<V> = Result<V, CommandError>;
+
+#[derive(Debug)]
+pub enum Parsed {
+    // boxed, a single rule is much larger than the list of them
+    Line(Box<Line>),
+    Lines(Vec<anyhow::Result<Line>>),
+}
+
+// a single `[ n] ...` row is parsed as one rule, anything else is treated as `ufw status numbered` output
+pub fn parse(input: &str) -> anyhow::Result<Parsed> {
+    let input = input.trim();
+    let mut lines = input.lines();
+
+    match (lines.next(), lines.next()) {
+        (Some(line), None) if line.starts_with('[') => {
+            Ok(Parsed::Line(Box::new(numbered::parse(line)?)))
+        }
+        _ => {
+            Ok(Parsed::Lines(numbered::parse_status(input)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dispatches_by_input_shape() {
+        let line = "[ 1] 22/tcp                     ALLOW IN    Anywhere";
+        assert!(matches!(parse(line), Ok(Parsed::Line(_))));
+
+        let status = format!("Status: active\n\n     To                         Action      From\n     --                         ------      ----\n{}\n\
+            [ 2] 80/tcp                     DENY IN     10.0.0.0/8\n", line);
+        match parse(&status) {
+            Ok(Parsed::Lines(lines)) => assert!(lines.len() == 2 && lines.iter().all(|line| line.is_ok())),
+            other => panic!("expected several lines, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_errors_have_a_message_with_their_payload() {
+        let payload = "payload".to_string();
+        let with_payload = [
+            ParseError::InvalidPortRange(payload.clone()),
+            ParseError::PortNotANumber(payload.clone()),
+            ParseError::NumberAfterProtocol(payload.clone()),
+            ParseError::InvalidProtocol(payload.clone()),
+            ParseError::IOError(payload.clone()),
+            ParseError::InvalidLoggingLevel(payload.clone()),
+            ParseError::WrongRuleDirection(payload.clone()),
+            ParseError::WrongRuleType(payload.clone()),
+            ParseError::InvalidDefaults(payload.clone()),
+            ParseError::InvalidReportFormat(payload.clone()),
+            ParseError::MissingSetting(payload.clone()),
+            ParseError::InvalidSetting(payload.clone()),
+            ParseError::InvalidStatusRow(payload.clone()),
+            ParseError::InvalidRuleSpec(payload.clone()),
+            ParseError::NegatedRule(payload.clone()),
+            ParseError::InvalidVersion(payload.clone()),
+            ParseError::InvalidAppInfo(payload.clone()),
+        ];
+        for error in with_payload.iter() {
+            assert!(error.to_string().ends_with(": payload"), "{:?}", error);
+        }
+
+        let without_payload = [ParseError::FileNotFound, ParseError::PortsSectionEmpty, ParseError::MissingTitle,
+            ParseError::MissingDescription, ParseError::MissingPorts, ParseError::EmptyPortsSection];
+        for error in without_payload.iter() {
+            assert!(!error.to_string().is_empty(), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn io_and_integer_errors_convert_with_the_question_mark() {
+        fn read(path: &str) -> ParseResult<String> {
+            Ok(std::fs::read_to_string(path)?)
+        }
+        fn port(value: &str) -> ParseResult<u16> {
+            Ok(value.parse::<u16>()?)
+        }
+
+        assert!(matches!(read("/nonexistent/ufw.conf"), Err(ParseError::IOError(_))));
+        assert!(matches!(port("http"), Err(ParseError::PortNotANumber(_))));
+        assert_eq!(port("22").unwrap(), 22);
+    }
+}
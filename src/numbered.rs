@@ -1,61 +1,40 @@
 use std::convert::TryFrom;
 use std::fmt::Formatter;
+use std::io::BufRead;
 use std::net::IpAddr;
 use std::str::{FromStr, Split};
 
 use anyhow::{Context, Error, Result};
 use pest::iterators::Pair;
 use pest::Parser;
+#[cfg(feature = "serialize")]
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{ParseError, Protocol};
+use crate::ufw::{Address as RuleAddress, IpVersion, quote_argument, RateLimit, RuleDirection, RuleEntry, RuleType};
 
 #[derive(Parser)]
 #[grammar = "address.pest"]
 pub struct AddressParser;
 
 #[derive(Debug, Eq, PartialOrd, PartialEq)]
-pub enum Protocol {
-    TCP,
-    UDP,
-    ANY,
-}
-
-#[derive(Debug, Eq, PartialOrd, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Modifier {
     ALLOW,
     DENY,
+    REJECT,
+    LIMIT,
 }
 
 #[derive(Debug, Eq, PartialOrd, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Direction {
     IN,
     OUT,
+    FWD,
     BOTH,
 }
 
-impl std::fmt::Display for Protocol {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            Protocol::TCP => { "tcp" }
-            Protocol::UDP => { "udp" }
-            Protocol::ANY => { "ANY" }
-        };
-
-        write!(f, "{}", s)
-    }
-}
-
-impl TryFrom<&str> for Protocol {
-    type Error = Error;
-
-    fn try_from(value: &str) -> Result<Self> {
-        match value {
-            "tcp" => Ok(Protocol::TCP),
-            "udp" => Ok(Protocol::UDP),
-            "" => Ok(Protocol::ANY),
-            _ => Err(anyhow!("unknown protocol"))
-        }
-    }
-}
-
 impl TryFrom<Option<&str>> for Direction {
     type Error = Error;
 
@@ -68,6 +47,7 @@ impl TryFrom<Option<&str>> for Direction {
                 match value {
                     "OUT" => Ok(Direction::OUT),
                     "IN" => Ok(Direction::IN),
+                    "FWD" => Ok(Direction::FWD),
                     "" => Ok(Direction::BOTH),
                     _ => Err(anyhow!("unknown direction"))
                 }
@@ -83,31 +63,107 @@ impl TryFrom<&str> for Modifier {
         match value {
             "ALLOW" => Ok(Modifier::ALLOW),
             "DENY" => Ok(Modifier::DENY),
+            "REJECT" => Ok(Modifier::REJECT),
+            "LIMIT" => Ok(Modifier::LIMIT),
             _ => Err(anyhow!("unknown modifier"))
         }
     }
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Address {
     addr: Option<IpAddr>,
     cidr: Option<u8>,
     port: Option<u16>,
+    // set for ranges like `6000:6010/tcp`, `port` is the start of the range then
+    end_port: Option<u16>,
     protocol: Protocol,
+    // the profile name of rules like `ufw allow OpenSSH`, depending on the version ufw prints it instead of its ports
+    application: Option<String>,
 }
 
-impl From<(Option<IpAddr>, Option<u16>, Option<u8>, Option<Protocol>)> for Address {
-    fn from(v: (Option<IpAddr>, Option<u16>, Option<u8>, Option<Protocol>)) -> Self {
-        let (mut addr, port, cidr, proto) = v;
-        if addr.is_none() && port.is_some() {
-            addr = Some(IpAddr::from([0, 0, 0, 0]))
+impl TryFrom<(Option<IpAddr>, Option<u16>, Option<u8>, Option<Protocol>)> for Address {
+    type Error = Error;
+
+    fn try_from(v: (Option<IpAddr>, Option<u16>, Option<u8>, Option<Protocol>)) -> Result<Self> {
+        // a side which only shows a port (`8080/tcp`) has no address at all
+        let (addr, port, cidr, proto) = v;
+
+        // `parse_cidr` doesn't know the address yet
+        if let (Some(addr), Some(cidr)) = (addr, cidr) {
+            let max = if addr.is_ipv6() { 128 } else { 32 };
+            if cidr > max {
+                Err(anyhow!("cidr must be >= 0 && <= {}", max))?
+            }
         }
 
-        Address {
+        Ok(Address {
             addr,
             cidr,
             port,
+            end_port: None,
             protocol: proto.unwrap_or(Protocol::ANY),
+            application: None,
+        })
+    }
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let address = match self.addr {
+            Some(addr) if !addr.is_unspecified() => match self.cidr {
+                Some(cidr) => Some(format!("{}/{}", addr, cidr)),
+                None => Some(addr.to_string()),
+            },
+            _ => None,
+        };
+        let port = self.port.map(|port| {
+            let port = match self.end_port {
+                Some(end_port) => format!("{}:{}", port, end_port),
+                None => port.to_string(),
+            };
+            match self.protocol {
+                Protocol::ANY => port,
+                ref proto => format!("{}/{}", port, proto),
+            }
+        });
+
+        let s = match (address, port.or_else(|| self.application.clone())) {
+            (Some(address), Some(port)) => format!("{} {}", address, port),
+            (None, Some(port)) => port,
+            (address, None) => {
+                let address = address.unwrap_or_else(|| "Anywhere".to_string());
+                match self.protocol {
+                    Protocol::ANY => address,
+                    ref proto => format!("{}/{}", address, proto),
+                }
+            }
+        };
+
+        // ufw only marks the unspecified address as v6, explicit addresses are recognizable on their own
+        match self.addr {
+            Some(addr) if addr.is_ipv6() && addr.is_unspecified() => write!(f, "{} (v6)", s),
+            _ => write!(f, "{}", s),
+        }
+    }
+}
+
+impl Address {
+    fn spec_address(&self) -> String {
+        match (self.addr, self.cidr) {
+            (Some(addr), _) if addr.is_unspecified() => "any".to_string(),
+            (Some(addr), Some(cidr)) => format!("{}/{}", addr, cidr),
+            (Some(addr), None) => addr.to_string(),
+            _ => "any".to_string(),
+        }
+    }
+
+    fn spec_port(&self) -> Option<String> {
+        match (self.port, self.end_port) {
+            (Some(port), Some(end_port)) => Some(format!("{}:{}", port, end_port)),
+            (Some(port), None) => Some(port.to_string()),
+            _ => None,
         }
     }
 }
@@ -126,42 +182,141 @@ fn parse_portp(mut s: Split<&str>) -> Result<(u16, Protocol)> {
     let port = s.next().unwrap().parse::<u16>().context("port must be >= 0 && <= 65535")?;
 
     // no protocol specified -> ANY
-    let proto = Protocol::try_from(s.next().unwrap_or(""))?;
+    let proto = Protocol::lenient(s.next().unwrap_or(""));
 
     Ok((port, proto))
 }
 
+fn parse_portrange(mut s: Split<&str>) -> Result<(u16, u16, Protocol)> {
+    // pest ensures `start:end/proto`
+    let mut range = s.next().unwrap().split(":");
+    let start = range.next().unwrap().parse::<u16>().context("port must be >= 0 && <= 65535")?;
+    let end = range.next().unwrap().parse::<u16>().context("port must be >= 0 && <= 65535")?;
+    if start > end {
+        Err(anyhow!("port range {}:{} ends before it starts", start, end))?
+    }
+
+    let proto = Protocol::lenient(s.next().unwrap_or(""));
+
+    Ok((start, end, proto))
+}
+
 fn parse_cidr(s: &str) -> Result<u8> {
     let x: u8 = s.parse::<u8>().map_err(|e| anyhow::Error::from(e))?;
 
-    if x > 32 {
-        Err(anyhow!("cidr must be >= 0 && <= 32"))?
+    if x > 128 {
+        Err(anyhow!("cidr must be >= 0 && <= 128"))?
     }
 
     Ok(x)
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Line {
     index: u16,
     to: Address,
     v6: bool,
-    action: Element,
-    device: Option<String>,
+    log: bool,
+    log_all: bool,
+    modifier: Modifier,
+    direction: Direction,
+    // route rules can have both, `in` and `out` rules only the one matching their direction
+    in_interface: Option<String>,
+    out_interface: Option<String>,
     from: Address,
+    comment: Option<String>,
 }
 
 impl Line {
-    fn new(index: u16, to: Address, v6: bool, action: Element, device: Option<String>, from: Address) -> Line {
-        Line {
-            index,
-            to,
-            v6,
-            action,
-            device,
-            from,
+    pub fn modifier(&self) -> &Modifier {
+        &self.modifier
+    }
+
+    pub fn direction(&self) -> &Direction {
+        &self.direction
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        match self.modifier {
+            Modifier::LIMIT => Some(RateLimit::default()),
+            _ => None,
         }
     }
+
+    pub fn in_interface(&self) -> Option<&str> {
+        self.in_interface.as_deref()
+    }
+
+    pub fn out_interface(&self) -> Option<&str> {
+        self.out_interface.as_deref()
+    }
+
+    // the arguments `ufw` needs to recreate this rule, in the order of ufw's full syntax:
+    // [route] allow|deny|reject|limit [in|out [on INTERFACE]] [log|log-all] [proto PROTOCOL] from ADDRESS [port PORT] to ADDRESS [port PORT]
+    pub fn to_rule_spec(&self) -> String {
+        let mut spec = vec![];
+
+        let modifier = match self.modifier {
+            Modifier::DENY => "deny",
+            Modifier::ALLOW => "allow",
+            Modifier::REJECT => "reject",
+            Modifier::LIMIT => "limit",
+        };
+
+        match self.direction {
+            Direction::FWD => {
+                spec.extend(vec!["route".to_string(), modifier.to_string()]);
+                if let Some(interface) = &self.in_interface {
+                    spec.push(format!("in on {}", interface));
+                }
+                if let Some(interface) = &self.out_interface {
+                    spec.push(format!("out on {}", interface));
+                }
+            }
+            _ => {
+                spec.push(modifier.to_string());
+                match (&self.direction, &self.in_interface, &self.out_interface) {
+                    (Direction::OUT, _, Some(interface)) => spec.push(format!("out on {}", interface)),
+                    (Direction::OUT, _, None) => spec.push("out".to_string()),
+                    (_, Some(interface), _) => spec.push(format!("in on {}", interface)),
+                    (Direction::IN, None, _) => spec.push("in".to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        if self.log_all {
+            spec.push("log-all".to_string());
+        } else if self.log {
+            spec.push("log".to_string());
+        }
+
+        match (&self.to.protocol, &self.from.protocol) {
+            (Protocol::ANY, Protocol::ANY) => {}
+            (Protocol::ANY, proto) | (proto, _) => spec.push(format!("proto {}", proto)),
+        }
+
+        for (keyword, address) in [("from", &self.from), ("to", &self.to)].iter() {
+            spec.push(format!("{} {}", keyword, address.spec_address()));
+            if let Some(port) = address.spec_port() {
+                spec.push(format!("port {}", port));
+            }
+            if let Some(application) = &address.application {
+                spec.push(format!("app {}", quote_argument(application.clone())));
+            }
+        }
+
+        if let Some(comment) = &self.comment {
+            spec.push(format!("comment {}", quote_argument(comment.clone())));
+        }
+
+        spec.join(" ")
+    }
 }
 
 impl TryFrom<Vec<Element>> for Line {
@@ -171,15 +326,25 @@ impl TryFrom<Vec<Element>> for Line {
         let mut toblock = true;
         let mut index = 0;
         let mut to = (None, None, None, None);
-        let mut action = Element::V6;
+        let mut action = None;
         let mut v6 = false;
-        let mut device = String::new();
+        let mut log = false;
+        let mut log_all = false;
+        let mut to_device = None;
+        let mut from_device = None;
+        let mut to_end_port = None;
+        let mut from_end_port = None;
+        let mut to_application = None;
+        let mut from_application = None;
         let mut from = (None, None, None, None);
+        let mut to_anywhere = false;
+        let mut from_anywhere = false;
+        let mut comment = None;
 
         for e in es {
             match e {
                 Element::Index(i) => {
-                    index = i
+                    index = i?
                 }
                 Element::ToFrom(val) => {
                     for x in val {
@@ -199,6 +364,16 @@ impl TryFrom<Vec<Element>> for Line {
                                     from = (from.0, Some(port), from.2, Some(proto))
                                 }
                             }
+                            Element::PortRangeProtocol(res) => {
+                                let (port, end_port, proto) = res?;
+                                if toblock {
+                                    to = (to.0, Some(port), to.2, Some(proto));
+                                    to_end_port = Some(end_port)
+                                } else {
+                                    from = (from.0, Some(port), from.2, Some(proto));
+                                    from_end_port = Some(end_port)
+                                }
+                            }
                             Element::CIDR(cidr) => {
                                 if toblock {
                                     to = (to.0, to.1, Some(cidr?), to.3)
@@ -227,21 +402,49 @@ impl TryFrom<Vec<Element>> for Line {
                                     from = (from.0, Some(port?), from.2, from.3)
                                 }
                             }
-                            _ => unimplemented!("{:#?}", x)
+                            Element::Application(name) => {
+                                if toblock {
+                                    to_application = Some(name)
+                                } else {
+                                    from_application = Some(name)
+                                }
+                            }
+                            // resolved below, once the ip version is known
+                            Element::Anywhere => {
+                                if toblock {
+                                    to_anywhere = true
+                                } else {
+                                    from_anywhere = true
+                                }
+                            }
+                            x => Err(anyhow!("unexpected element in a to/from block: {:?}", x))?
                         }
                     }
                 }
                 Element::Port(_) => {}
+                // the action separates both sides, everything before it belongs to the `to` side
                 Element::Device(dev) => {
-                    device = dev;
-                    toblock = false
+                    if toblock {
+                        to_device = Some(dev)
+                    } else {
+                        from_device = Some(dev)
+                    }
                 }
                 Element::V6 => {
-                    v6 = true;
-                    toblock = false
+                    v6 = true
+                }
+                Element::Log => {
+                    log = true
                 }
-                Element::Action(port, proto) => {
-                    action = Element::Action(Ok(port?), Ok(proto?));
+                Element::LogAll => {
+                    log_all = true
+                }
+                Element::Annotation(_) => {}
+                Element::Comment(text) => {
+                    comment = Some(text)
+                }
+                Element::Action(modifier, direction) => {
+                    action = Some((modifier?, direction?));
                     toblock = false
                 }
                 Element::Modifier(_) => {}
@@ -251,26 +454,64 @@ impl TryFrom<Vec<Element>> for Line {
             }
         }
 
-        let (toaddr, topp, tocidr, toproto) = to;
-        let to = Address::try_from((toaddr, topp, tocidr, toproto));
-        let (fromaddr, fromp, fromcidr, fromproto) = from;
-        let from = Address::try_from((fromaddr, fromp, fromcidr, fromproto));
+        // ufw only prints `(v6)` for rules without an explicit ipv6 address
+        let v6 = v6 || [to.0, from.0].iter().any(|addr| addr.is_some_and(|addr| addr.is_ipv6()));
+        // `Anywhere` is 0.0.0.0/0, `Anywhere (v6)` is ::/0
+        let anywhere = if v6 { IpAddr::from([0u16; 8]) } else { IpAddr::from([0, 0, 0, 0]) };
+        let resolve = |(addr, port, cidr, proto), is_anywhere: bool| match is_anywhere {
+            true => (Some(anywhere), port, Some(0), proto),
+            false => (addr, port, cidr, proto),
+        };
+
+        let (toaddr, topp, tocidr, toproto) = resolve(to, to_anywhere);
+        let to = Address::try_from((toaddr, topp, tocidr, toproto)).map(|to| Address { end_port: to_end_port, application: to_application, ..to });
+        let (fromaddr, fromp, fromcidr, fromproto) = resolve(from, from_anywhere);
+        let from = Address::try_from((fromaddr, fromp, fromcidr, fromproto)).map(|from| Address { end_port: from_end_port, application: from_application, ..from });
+        let (to, from) = (to?, from?);
+        let (modifier, direction) = action.ok_or_else(|| anyhow!("missing action"))?;
+        // the interface on the `from` side of a route is where packets come in, the one on the `to` side where they leave.
+        // other rules print theirs on either side
+        let (in_interface, out_interface) = match direction {
+            Direction::FWD => (from_device, to_device),
+            Direction::OUT => (None, to_device.or(from_device)),
+            _ => (to_device.or(from_device), None),
+        };
 
-        Ok(Line::new(index, to?, v6, action, Some(device), from?))
+        Ok(Line {
+            index,
+            to,
+            v6,
+            log,
+            log_all,
+            modifier,
+            direction,
+            in_interface,
+            out_interface,
+            from,
+            comment,
+        })
     }
 }
 
 #[derive(Debug)]
 pub enum Element {
-    Index(u16),
+    Index(Result<u16>),
     Ipv4Address(Result<IpAddr>),
     Address(Result<IpAddr>),
     Protocol(Result<Protocol>),
     PortProtocol(Result<(u16, Protocol)>),
+    PortRangeProtocol(Result<(u16, u16, Protocol)>),
     ToFrom(Vec<Element>),
     Port(Result<u16>),
     Device(String),
+    Application(String),
     V6,
+    Log,
+    LogAll,
+    // annotations which ufw prints in parentheses but which aren't modeled (yet)
+    Annotation(String),
+    Comment(String),
+    Anywhere,
     Action(Result<Modifier>, Result<Direction>),
     Modifier(Result<Modifier>),
     Direction(Result<Direction>),
@@ -279,7 +520,159 @@ pub enum Element {
     END,
 }
 
+// the v4 and v6 rule ufw creates for a single `ufw allow ...` which doesn't name an explicit address
+#[derive(Debug)]
+pub struct RulePair<'a> {
+    v4: Option<&'a Line>,
+    v6: Option<&'a Line>,
+}
+
+impl<'a> RulePair<'a> {
+    pub fn v4(&self) -> Option<&'a Line> {
+        self.v4
+    }
+
+    pub fn v6(&self) -> Option<&'a Line> {
+        self.v6
+    }
+}
+
+// the numbered table and a RuleEntry describe the same rule, a RuleEntry however only has
+// one protocol for both sides
+impl TryFrom<&Line> for RuleEntry {
+    type Error = ParseError;
+
+    fn try_from(line: &Line) -> Result<Self, Self::Error> {
+        let invalid = |reason: &str| ParseError::InvalidStatusRow(format!("rule {}: {}", line.index, reason));
+
+        let typ = match line.modifier {
+            Modifier::ALLOW => RuleType::ALLOW,
+            Modifier::DENY => RuleType::DENY,
+            Modifier::REJECT => RuleType::REJECT,
+            Modifier::LIMIT => RuleType::LIMIT,
+        };
+        // ufw applies rules without a direction to incoming traffic
+        let direction = match line.direction {
+            Direction::OUT => RuleDirection::OUT,
+            Direction::FWD => RuleDirection::FWD,
+            Direction::IN | Direction::BOTH => RuleDirection::IN,
+        };
+        // routes keep both interfaces, other rules only have the one of their direction
+        let (interface, out_interface) = match direction {
+            RuleDirection::FWD => (line.in_interface.clone(), line.out_interface.clone()),
+            RuleDirection::OUT => (line.out_interface.clone(), None),
+            RuleDirection::IN => (line.in_interface.clone(), None),
+        };
+        let proto = match (&line.to.protocol, &line.from.protocol) {
+            (Protocol::ANY, proto) | (proto, Protocol::ANY) => proto.clone(),
+            (to, from) if to == from => to.clone(),
+            _ => Err(invalid("both sides use a different protocol"))?,
+        };
+
+        let address = |address: &Address| match address.addr {
+            Some(addr) if !addr.is_unspecified() => Some(RuleAddress {
+                addr,
+                cidr: address.cidr.unwrap_or(if addr.is_ipv6() { 128 } else { 32 }),
+            }),
+            _ => None,
+        };
+
+        Ok(RuleEntry {
+            interface,
+            out_interface,
+            source_address: address(&line.from),
+            destination_address: address(&line.to),
+            source_port: line.from.port,
+            destination_port: line.to.port,
+            source_end_port: line.from.end_port,
+            destination_end_port: line.to.end_port,
+            source_application: line.from.application.clone(),
+            destination_application: line.to.application.clone(),
+            proto,
+            ip_version: Some(if line.v6 { IpVersion::V6 } else { IpVersion::V4 }),
+            number: line.index,
+            comment: line.comment.clone(),
+            ..RuleEntry::new(typ, direction)
+        })
+    }
+}
+
+impl Address {
+    // everything but the address, which is `Anywhere` and `Anywhere (v6)` for companion rules
+    fn same_match(&self, other: &Address) -> bool {
+        let anywhere = |address: &Address| address.addr.is_none_or(|addr| addr.is_unspecified());
+
+        anywhere(self) && anywhere(other)
+            && self.port == other.port
+            && self.end_port == other.end_port
+            && self.protocol == other.protocol
+            && self.application == other.application
+    }
+}
+
+impl Line {
+    fn is_v6(&self) -> bool {
+        self.v6
+    }
+
+    fn is_companion(&self, other: &Line) -> bool {
+        self.modifier == other.modifier
+            && self.direction == other.direction
+            && self.to.same_match(&other.to)
+            && self.from.same_match(&other.from)
+            && self.in_interface == other.in_interface
+            && self.out_interface == other.out_interface
+            && self.log == other.log
+            && self.log_all == other.log_all
+    }
+}
+
+// pairs every v6 rule with the first unpaired v4 rule that only differs by its ip version, rules without
+// a companion (e.g. explicit addresses) are returned on their own. the pairs are ordered by their first rule
+pub fn group_rule_pairs(lines: &[Line]) -> Vec<RulePair<'_>> {
+    pair_companions(lines, Line::is_v6, Line::is_companion)
+        .into_iter()
+        .map(|(v4, v6)| RulePair { v4, v6 })
+        .collect()
+}
+
+// the pairing of `group_rule_pairs`, shared with the rules of `Ufw` which are already converted into RuleEntries
+pub(crate) fn pair_companions<T, V, C>(rules: &[T], is_v6: V, is_companion: C) -> Vec<(Option<&T>, Option<&T>)>
+    where V: Fn(&T) -> bool, C: Fn(&T, &T) -> bool {
+    let mut pairs: Vec<(Option<&T>, Option<&T>)> = vec![];
+
+    for rule in rules {
+        if !is_v6(rule) {
+            pairs.push((Some(rule), None));
+            continue;
+        }
+
+        let companion = pairs
+            .iter_mut()
+            .find(|(v4, v6)| v6.is_none() && v4.is_some_and(|v4| is_companion(v4, rule)));
+        match companion {
+            Some(pair) => pair.1 = Some(rule),
+            None => pairs.push((None, Some(rule))),
+        }
+    }
+
+    pairs
+}
+
 pub fn parse(line: &str) -> Result<Line> {
+    let line = parse_lenient(line)?;
+
+    for protocol in [&line.to.protocol, &line.from.protocol].iter() {
+        if let Protocol::Other(proto) = protocol {
+            Err(anyhow!("unknown protocol: {}", proto))?
+        }
+    }
+
+    Ok(line)
+}
+
+// unknown protocols are kept as `Protocol::Other` instead of failing the whole line
+pub fn parse_lenient(line: &str) -> Result<Line> {
     let elements = AddressParser::parse(Rule::line, &(line.to_owned() + " "))?.next().unwrap()
         .into_inner()
         .into_iter()
@@ -288,10 +681,30 @@ pub fn parse(line: &str) -> Result<Line> {
     Line::try_from(elements)
 }
 
+pub fn parse_status(status: &str) -> Vec<Result<Line>> {
+    status
+        .lines()
+        .filter(|line| line.trim_start().starts_with('['))
+        .map(parse)
+        .collect()
+}
+
+// like `parse_status` but reads one line at a time instead of holding the whole output in memory
+pub fn parse_reader<R: BufRead>(reader: R) -> impl Iterator<Item=Result<Line>> {
+    reader
+        .lines()
+        .filter_map(|line| match line {
+            Ok(line) if line.trim_start().starts_with('[') => Some(parse(&line)),
+            Ok(_) => None,
+            Err(err) => Some(Err(anyhow::Error::from(err))),
+        })
+}
+
 pub fn parse_line(r: Pair<Rule>) -> Element {
     match r.as_rule() {
         Rule::index => {
-            let index = parse_index(r).unwrap();
+            // pest only checks for digits, the index can still be too large for an u16
+            let index = parse_index(r).context("Rule::index");
 
             Element::Index(index)
         }
@@ -307,26 +720,39 @@ pub fn parse_line(r: Pair<Rule>) -> Element {
                 let s = inner.unwrap().as_str();
                 IpAddr::from_str(s).context(format!("Rule::address: {}", s))
             } else {
-                // needs to be ::/0 for ipv6
-                Ok(IpAddr::from([0, 0, 0, 0]))
+                // the family of `Anywhere` is only known once the whole line has been seen
+                return Element::Anywhere;
             }.map_err(|e| anyhow::Error::from(e).context("Rule::address"));
 
             Element::Address(address)
         }
         Rule::protosuffix => {
-            // pest ensures a slash at the start -> empty first element in iterator
-            let proto = Protocol::try_from(r.as_str().split("/").next().unwrap_or("")).context("Rule::protosuffix");
+            // pest ensures a slash at the start -> empty first element in iterator, the protocol is the second one
+            let proto = Ok(Protocol::lenient(r.as_str().split("/").nth(1).unwrap_or("")));
 
             Element::Protocol(proto)
         }
         Rule::portp => {
             let s = r.as_str();
-            let res = parse_portp(s.split("/")).map_err(|e| anyhow::Error::from(e).context(format!("Rule::portp {}", s)));
+            if s.contains(':') {
+                let res = parse_portrange(s.split("/")).context(format!("Rule::portp {}", s));
 
-            Element::PortProtocol(res)
+                Element::PortRangeProtocol(res)
+            } else {
+                let res = parse_portp(s.split("/")).map_err(|e| anyhow::Error::from(e).context(format!("Rule::portp {}", s)));
+
+                Element::PortProtocol(res)
+            }
+        }
+        // a range without its protocol stands for any, like a port on its own
+        Rule::portrange => {
+            let s = r.as_str();
+            let res = parse_portrange(s.split("/")).context(format!("Rule::portrange {}", s));
+
+            Element::PortRangeProtocol(res)
         }
         Rule::proto => {
-            let proto = Protocol::try_from(r.as_str()).map_err(|e| anyhow::Error::from(e).context(format!("Rule::proto {:?}", r)));
+            let proto = Ok(Protocol::lenient(r.as_str()));
 
             Element::Protocol(proto)
         }
@@ -348,6 +774,12 @@ pub fn parse_line(r: Pair<Rule>) -> Element {
 
             Element::Port(port)
         }
+        Rule::application => {
+            Element::Application(r.as_str().to_string())
+        }
+        Rule::application_word => {
+            Element::Application(r.as_str().to_string())
+        }
         Rule::device => {
             let device = r.as_str();
 
@@ -360,8 +792,21 @@ pub fn parse_line(r: Pair<Rule>) -> Element {
             let device = split.next().unwrap();
             Element::Device(device.to_string())
         }
-        Rule::v6 => {
-            Element::V6
+        Rule::comment => {
+            Element::Comment(r.as_str().trim_start_matches('#').trim().to_string())
+        }
+        Rule::annotation => {
+            let name = r.into_inner().next().map(|n| n.as_str()).unwrap_or("");
+
+            match name {
+                "v6" => Element::V6,
+                "log" => Element::Log,
+                "log-all" => Element::LogAll,
+                _ => Element::Annotation(name.to_string()),
+            }
+        }
+        Rule::annotation_name => {
+            Element::Annotation(r.as_str().to_string())
         }
         Rule::action => {
             let mut i = r.into_inner();
@@ -386,16 +831,378 @@ pub fn parse_line(r: Pair<Rule>) -> Element {
         }
         Rule::cidrprot => {
             let mut inner = r.into_inner();
-            let cidr = inner.next().context("cidr must be there in cidrproto").and_then(|x| x.as_str().parse::<u8>().map_err(|e| anyhow::Error::from(e)));
-            let proto = Protocol::try_from(inner.next().unwrap().as_str()).map_err(|e| anyhow::Error::from(e));
+            let cidr = inner.next().context("cidr must be there in cidrproto").and_then(|x| parse_cidr(x.as_str()));
+            let proto = Ok(Protocol::lenient(inner.next().unwrap().as_str()));
 
             Element::CIDRProto(cidr, proto)
         }
         Rule::line => unimplemented!("can't parse another line in `parse_line`"),
-        Rule::hex => unimplemented!("can't parse hex in `parse_line`"),
-        Rule::ipv6_address => unimplemented!("can't parse another ipv6_address in `parse_line`"),
+        Rule::ipv6_address => {
+            let s = r.as_str();
+            let ipv6_address = IpAddr::from_str(s).map_err(|e| anyhow::Error::from(e).context(format!("Rule::ipv6_address: {:?}", s)));
+
+            Element::Address(ipv6_address)
+        }
         Rule::EOI => {
             Element::END
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Read};
+
+    const LINES: [&str; 4] = [
+        "[ 1] 22/tcp                     ALLOW IN    Anywhere",
+        "[ 2] 6000:6007/udp on eth0      DENY OUT    10.0.0.0/8                 # x11",
+        "[ 3] Anywhere on eth1           ALLOW FWD   Anywhere on eth0",
+        "[ 4] 22/tcp (v6)                LIMIT IN    Anywhere (v6)              (log)",
+    ];
+
+    #[test]
+    fn parse_line_returns_port_range_errors() {
+        let range = |s: &str| parse_line(AddressParser::parse(Rule::portrange, s).unwrap().next().unwrap());
+
+        assert!(matches!(range("6000:6007"), Element::PortRangeProtocol(Ok((6000, 6007, Protocol::ANY)))));
+        assert!(matches!(range("6007:6000"), Element::PortRangeProtocol(Err(_))));
+        assert!(matches!(range("6000:70000"), Element::PortRangeProtocol(Err(_))));
+    }
+
+    // a small xorshift instead of pulling in a property testing crate, seeded so failures can be reproduced
+    #[test]
+    fn parse_never_panics_on_arbitrary_input() {
+        let alphabet: Vec<char> = "[] 0123456789:/.,#()-abcdefghijklmnopqrstuvwxyzALLOWDENYINOUTFWDv6\u{e4}\t".chars().collect();
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+
+        for _ in 0..20_000 {
+            let mut line: Vec<char> = LINES[next(LINES.len())].chars().collect();
+            for _ in 0..=next(6) {
+                let position = next(line.len() + 1);
+                match next(3) {
+                    0 if position < line.len() => { line.remove(position); }
+                    1 => line.insert(position, alphabet[next(alphabet.len())]),
+                    _ => line.truncate(position),
+                }
+            }
+            let line: String = line.into_iter().collect();
+
+            let _ = parse(&line);
+            let _ = parse_status(&line);
+        }
+    }
+
+    #[test]
+    fn parse_maps_known_annotations_and_skips_unknown_ones() {
+        let v6 = parse("[ 1] 22/tcp (v6)                ALLOW IN    Anywhere (v6)").unwrap();
+        assert!(v6.v6 && !v6.log);
+
+        let log = parse("[ 2] 22/tcp                     ALLOW IN    Anywhere                   (log)").unwrap();
+        assert!(log.log && !log.log_all && !log.v6);
+
+        let log_all = parse("[ 3] 80/tcp                     DENY IN     Anywhere                   (log-all)").unwrap();
+        assert!(log_all.log_all);
+
+        let unknown = parse("[ 4] 443/tcp                    ALLOW IN    Anywhere                   (something-new)").unwrap();
+        assert!(!unknown.v6 && !unknown.log && !unknown.log_all);
+        assert_eq!(unknown.to.port, Some(443));
+    }
+
+    #[test]
+    fn parse_keeps_a_cidr_in_the_to_column() {
+        let line = parse("[ 2] 192.168.1.0/24             ALLOW IN    Anywhere").unwrap();
+
+        assert_eq!(line.to.addr, Some(IpAddr::from([192, 168, 1, 0])));
+        assert_eq!(line.to.cidr, Some(24));
+        assert_eq!(line.to.port, None);
+
+        let with_protocol = parse("[ 3] 10.0.0.0/8/tcp             DENY IN     Anywhere").unwrap();
+        assert_eq!((with_protocol.to.cidr, &with_protocol.to.protocol), (Some(8), &Protocol::TCP));
+        assert!(parse("[ 4] 10.0.0.0/33                ALLOW IN    Anywhere").is_err());
+    }
+
+    #[test]
+    fn parse_lenient_keeps_unknown_protocols() {
+        let row = "[ 1] 5000/sctp                  ALLOW IN    Anywhere";
+
+        assert!(parse(row).is_err());
+        let line = parse_lenient(row).unwrap();
+        assert_eq!(line.to.protocol, Protocol::Other("sctp".to_string()));
+        assert_eq!(line.to.port, Some(5000));
+    }
+
+    #[test]
+    fn parse_keeps_both_interfaces_of_a_route() {
+        let line = parse("[ 1] Anywhere on eth1           ALLOW FWD   Anywhere on eth0").unwrap();
+
+        assert_eq!(line.direction, Direction::FWD);
+        assert_eq!(line.in_interface(), Some("eth0"));
+        assert_eq!(line.out_interface(), Some("eth1"));
+        assert_eq!(line.to_rule_spec(), "route allow in on eth0 out on eth1 from any to any");
+    }
+
+    #[test]
+    fn address_renders_like_the_status_columns() {
+        let address = |addr: Option<IpAddr>, port, cidr, proto| Address::try_from((addr, port, cidr, proto)).unwrap().to_string();
+        let v4_anywhere = Some(IpAddr::from([0, 0, 0, 0]));
+        let v6_anywhere = Some(IpAddr::from([0u16; 8]));
+
+        assert_eq!(address(v4_anywhere, None, Some(0), None), "Anywhere");
+        assert_eq!(address(v6_anywhere, None, Some(0), None), "Anywhere (v6)");
+        assert_eq!(address(Some(IpAddr::from([192, 168, 1, 0])), None, Some(24), None), "192.168.1.0/24");
+        assert_eq!(address(None, Some(22), None, Some(Protocol::TCP)), "22/tcp");
+        assert_eq!(address(Some(IpAddr::from([10, 0, 0, 1])), Some(53), None, Some(Protocol::UDP)), "10.0.0.1 53/udp");
+        assert_eq!(address(v6_anywhere, Some(22), Some(0), Some(Protocol::TCP)), "22/tcp (v6)");
+        assert_eq!(address(v4_anywhere, None, Some(0), Some(Protocol::ESP)), "Anywhere/esp");
+    }
+
+    #[test]
+    fn parse_reads_hex_ipv6_addresses_without_panicking() {
+        let line = parse("[ 1] 2001:db8::1 22/tcp          ALLOW IN    fe80::/10").unwrap();
+        assert_eq!(line.to.addr, Some("2001:db8::1".parse().unwrap()));
+        assert_eq!((line.from.addr, line.from.cidr), (Some("fe80::".parse().unwrap()), Some(10)));
+        assert!(line.v6);
+
+        // matches the grammar but isn't an address
+        assert!(parse("[ 2] abcd:::ffff:1:2:3:4:5        ALLOW IN    Anywhere").is_err());
+    }
+
+    #[test]
+    fn parse_accepts_v6_annotations_around_the_action() {
+        let after = parse("[ 1] 22/tcp                     ALLOW IN (v6) Anywhere").unwrap();
+        let before = parse("[ 2] 22/tcp (v6) ALLOW IN    Anywhere").unwrap();
+
+        for line in [after, before].iter() {
+            assert!(line.v6);
+            assert_eq!((&line.modifier, &line.direction), (&Modifier::ALLOW, &Direction::IN));
+            assert_eq!(line.to.port, Some(22));
+        }
+    }
+
+    // an endless `ufw status numbered` output, reading all of it never finishes
+    struct EndlessStatus {
+        row: u32,
+        pending: Vec<u8>,
+    }
+
+    impl Read for EndlessStatus {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.pending.is_empty() {
+                self.row += 1;
+                self.pending = format!("[{:>2}] {}/tcp                     ALLOW IN    Anywhere\n", self.row, self.row % 65535).into_bytes();
+            }
+            let length = buf.len().min(self.pending.len());
+            buf[..length].copy_from_slice(&self.pending[..length]);
+            self.pending.drain(..length);
+
+            Ok(length)
+        }
+    }
+
+    #[test]
+    fn parse_reader_parses_lazily() {
+        let header = "Status: active\n\n     To                         Action      From\n     --                         ------      ----\n";
+        let reader = BufReader::new(header.as_bytes().chain(EndlessStatus { row: 0, pending: vec![] }));
+
+        let lines: Vec<Line> = parse_reader(reader).skip(9_997).take(3).map(Result::unwrap).collect();
+        assert_eq!(lines.iter().map(|line| line.index).collect::<Vec<u16>>(), vec![9_998, 9_999, 10_000]);
+    }
+
+    #[test]
+    fn parse_mixes_a_range_and_a_single_port() {
+        let line = parse("[ 1] 10.0.0.1 6000:6010/tcp      ALLOW IN    10.0.0.2 1024/tcp").unwrap();
+
+        assert_eq!((line.to.port, line.to.end_port, &line.to.protocol), (Some(6000), Some(6010), &Protocol::TCP));
+        assert_eq!((line.from.port, line.from.end_port, &line.from.protocol), (Some(1024), None, &Protocol::TCP));
+        assert_eq!(line.to_rule_spec(), "allow in proto tcp from 10.0.0.2 port 1024 to 10.0.0.1 port 6000:6010");
+    }
+
+    #[test]
+    fn to_rule_spec_emits_the_command_form() {
+        let spec = |row: &str| parse(row).unwrap().to_rule_spec();
+
+        assert_eq!(spec("[ 1] 22/tcp                     ALLOW IN    Anywhere"), "allow in proto tcp from any to any port 22");
+        assert_eq!(spec("[ 2] 192.168.1.1 22/tcp on eth0 ALLOW IN    Anywhere"), "allow in on eth0 proto tcp from any to 192.168.1.1 port 22");
+        assert_eq!(spec("[ 3] Anywhere                   DENY OUT    10.0.0.0/8"), "deny out from 10.0.0.0/8 to any");
+        assert_eq!(spec("[ 4] 80                         REJECT IN   Anywhere"), "reject in from any to any port 80");
+    }
+
+    #[test]
+    fn group_rule_pairs_pairs_v4_and_v6_rules() {
+        let lines: Vec<Line> = [
+            "[ 1] 22/tcp                     ALLOW IN    Anywhere",
+            "[ 2] 10.0.0.1 80/tcp            ALLOW IN    Anywhere",
+            "[ 3] 22/tcp (v6)                ALLOW IN    Anywhere (v6)",
+        ].iter().map(|row| parse(row).unwrap()).collect();
+
+        let pairs = group_rule_pairs(&lines);
+        let indices: Vec<(Option<u16>, Option<u16>)> = pairs.iter().map(|pair| (pair.v4().map(|l| l.index), pair.v6().map(|l| l.index))).collect();
+        assert_eq!(indices, vec![(Some(1), Some(3)), (Some(2), None)]);
+    }
+
+    #[test]
+    fn parse_accepts_v6_prefixes_up_to_128() {
+        let line = parse("[ 1] 2001:db8::/64 22/tcp        ALLOW IN    2001:db8::1/128").unwrap();
+        assert_eq!((line.to.cidr, line.from.cidr), (Some(64), Some(128)));
+
+        assert!(parse("[ 2] 2001:db8::/129 22/tcp       ALLOW IN    Anywhere (v6)").is_err());
+    }
+
+    #[test]
+    fn parse_reads_an_ipv6_source() {
+        let line = parse("[ 5] 22/tcp (v6)                ALLOW IN    2001:db8::/32").unwrap();
+
+        assert!(line.is_v6());
+        assert_eq!(line.from.addr, Some(IpAddr::from_str("2001:db8::").unwrap()));
+        assert_eq!(line.from.cidr, Some(32));
+        assert_eq!((line.to.port, &line.to.protocol), (Some(22), &Protocol::TCP));
+    }
+
+    #[cfg(feature = "serialize")]
+    #[test]
+    fn serializes_a_parsed_line_and_back() {
+        let line = parse("[ 2] 192.168.1.1 22/tcp on eth0 ALLOW IN    10.0.0.0/8 # ssh").unwrap();
+
+        let value = toml::Value::try_from(&line).unwrap();
+        assert_eq!(value["to"]["addr"].as_str(), Some("192.168.1.1"));
+        assert_eq!(value["from"]["cidr"].as_integer(), Some(8));
+
+        let parsed: Line = value.try_into().unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", line));
+    }
+
+    #[test]
+    fn parse_resolves_the_action_into_modifier_and_direction() {
+        let line = parse("[ 1] 22/tcp                     ALLOW IN    Anywhere").unwrap();
+        assert_eq!(line.modifier(), &Modifier::ALLOW);
+        assert_eq!(line.direction(), &Direction::IN);
+
+        let line = parse("[ 2] Anywhere                   REJECT OUT  10.0.0.1").unwrap();
+        assert_eq!((line.modifier(), line.direction()), (&Modifier::REJECT, &Direction::OUT));
+    }
+
+    #[test]
+    fn parse_keeps_a_trailing_comment_with_spaces() {
+        let line = parse("[ 1] 22/tcp                     ALLOW IN    Anywhere                   # ssh from the office").unwrap();
+        assert_eq!(line.comment(), Some("ssh from the office"));
+
+        let rule = RuleEntry::try_from(&line).unwrap();
+        assert_eq!(rule.to_string(), "allow in proto tcp to any port 22 comment 'ssh from the office'");
+    }
+
+    #[test]
+    fn parse_reads_limit_and_reject_rows() {
+        let limit = parse("[ 1] 22/tcp                     LIMIT IN    Anywhere").unwrap();
+        assert_eq!((limit.modifier(), limit.direction()), (&Modifier::LIMIT, &Direction::IN));
+        assert_eq!(limit.rate_limit(), Some(RateLimit::default()));
+
+        let reject = parse("[ 2] 25/tcp                     REJECT OUT  Anywhere").unwrap();
+        assert_eq!((reject.modifier(), reject.direction()), (&Modifier::REJECT, &Direction::OUT));
+        assert_eq!(reject.rate_limit(), None);
+    }
+
+    #[test]
+    fn parse_reads_every_protocol_of_the_shared_type() {
+        for (token, protocol) in [("tcp", Protocol::TCP), ("udp", Protocol::UDP)].iter() {
+            let line = parse(&format!("[ 1] 22/{}                     ALLOW IN    Anywhere", token)).unwrap();
+            assert_eq!(&line.to.protocol, protocol);
+            assert_eq!(line.to_rule_spec(), format!("allow in proto {} from any to any port 22", token));
+        }
+        for (token, protocol) in [("ah", Protocol::AH), ("esp", Protocol::ESP), ("gre", Protocol::GRE), ("ipv6", Protocol::IPV6), ("igmp", Protocol::IGMP)].iter() {
+            let line = parse(&format!("[ 1] Anywhere/{}               ALLOW IN    Anywhere/{}", token, token)).unwrap();
+            assert_eq!((&line.to.protocol, &line.from.protocol), (protocol, protocol));
+        }
+
+        let line = parse("[ 1] 22                         ALLOW IN    Anywhere").unwrap();
+        assert_eq!(line.to.protocol, Protocol::ANY);
+    }
+
+    #[test]
+    fn parse_assigns_interfaces_to_their_side() {
+        let incoming = parse("[ 1] 22/tcp on eth0              ALLOW IN    Anywhere").unwrap();
+        assert_eq!((incoming.in_interface(), incoming.out_interface()), (Some("eth0"), None));
+
+        let outgoing = parse("[ 2] Anywhere                   ALLOW OUT   Anywhere on wg0").unwrap();
+        assert_eq!((outgoing.in_interface(), outgoing.out_interface()), (None, Some("wg0")));
+
+        let route = parse("[ 3] 10.0.0.0/8 on eth1         ALLOW FWD   Anywhere on eth0").unwrap();
+        assert_eq!((route.in_interface(), route.out_interface()), (Some("eth0"), Some("eth1")));
+    }
+
+    #[test]
+    fn parse_maps_anywhere_to_the_unspecified_networks() {
+        let v4 = parse("[ 1] Anywhere                   DENY IN     10.0.0.1").unwrap();
+        assert_eq!((v4.to.addr, v4.to.cidr), (Some(IpAddr::from([0, 0, 0, 0])), Some(0)));
+        assert!(!v4.is_v6());
+
+        let v6 = parse("[ 2] Anywhere (v6)              DENY IN     2001:db8::1").unwrap();
+        assert_eq!((v6.to.addr, v6.to.cidr), (Some(IpAddr::from([0u16; 8])), Some(0)));
+        assert!(v6.is_v6());
+    }
+
+    #[test]
+    fn parse_keeps_no_address_for_port_only_sides() {
+        let line = parse("[ 3] 8080/tcp                   DENY IN     Anywhere").unwrap();
+        assert_eq!(line.to.addr, None);
+        assert_eq!((line.to.port, &line.to.protocol), (Some(8080), &Protocol::TCP));
+
+        let rule = RuleEntry::try_from(&line).unwrap();
+        assert_eq!(rule.destination_address(), None);
+        assert_eq!(rule.to_string(), "deny in proto tcp to any port 8080");
+    }
+
+    #[test]
+    fn converts_a_parsed_line_into_a_rule_entry() {
+        let line = parse("[ 4] 192.168.1.1 22/tcp on eth0 DENY IN     10.0.0.0/8 1024/tcp").unwrap();
+        let rule = RuleEntry::try_from(&line).unwrap();
+
+        assert_eq!(rule.number(), 4);
+        assert_eq!((rule.action.typ(), rule.action.direction()), (&RuleType::DENY, &RuleDirection::IN));
+        assert_eq!(rule.interface(), Some("eth0"));
+        assert_eq!(rule.destination_address().map(ToString::to_string), Some("192.168.1.1".to_string()));
+        assert_eq!(rule.source_address().map(ToString::to_string), Some("10.0.0.0/8".to_string()));
+        assert_eq!((rule.source_port(), rule.destination_port(), rule.proto()), (Some(1024), Some(22), &Protocol::TCP));
+
+        // a RuleEntry has a single protocol for both sides
+        let mismatch = parse("[ 5] 53/udp                     ALLOW IN    Anywhere 1024/tcp").unwrap();
+        assert!(RuleEntry::try_from(&mismatch).is_err());
+    }
+
+    #[test]
+    fn to_rule_spec_quotes_the_comment_like_the_shell() {
+        let line = parse("[ 1] 22/tcp                     ALLOW IN    Anywhere                   # it's ssh").unwrap();
+
+        assert_eq!(line.to_rule_spec(), "allow in proto tcp from any to any port 22 comment 'it'\\''s ssh'");
+        assert_eq!(line.to_rule_spec().split(" comment ").nth(1), RuleEntry::try_from(&line).unwrap().to_string().split(" comment ").nth(1));
+    }
+
+    #[test]
+    fn parses_application_profile_names() {
+        let ssh = parse("[ 1] OpenSSH ALLOW IN Anywhere").unwrap();
+        assert_eq!(ssh.to.application.as_deref(), Some("OpenSSH"));
+        assert!(!ssh.is_v6());
+        assert_eq!(ssh.to_rule_spec(), "allow in from any to any app OpenSSH");
+
+        let nginx = parse("[ 2] Nginx Full (v6) ALLOW IN Anywhere (v6)").unwrap();
+        assert_eq!(nginx.to.application.as_deref(), Some("Nginx Full"));
+        assert!(nginx.is_v6());
+        assert_eq!(nginx.to_rule_spec(), "allow in from any to any app 'Nginx Full'");
+
+        let padded = parse("[ 3] Nginx Full                 ALLOW IN    10.0.0.1 OpenSSH           # web").unwrap();
+        assert_eq!((padded.to.application.as_deref(), padded.from.application.as_deref()), (Some("Nginx Full"), Some("OpenSSH")));
+        assert_eq!(padded.comment(), Some("web"));
+        let rule = RuleEntry::try_from(&padded).unwrap();
+        assert_eq!((rule.destination_application(), rule.source_application()), (Some("Nginx Full"), Some("OpenSSH")));
+        assert_eq!(rule.source_address().map(ToString::to_string), Some("10.0.0.1".to_string()));
+
+        let on_interface = parse("[ 4] OpenSSH on eth0            ALLOW IN    Anywhere").unwrap();
+        assert_eq!((on_interface.to.application.as_deref(), on_interface.in_interface()), (Some("OpenSSH"), Some("eth0")));
+    }
+}
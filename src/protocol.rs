@@ -0,0 +1,94 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+#[cfg(feature = "serialize")]
+use serde_derive::{Deserialize, Serialize};
+
+use crate::ParseError;
+
+#[derive(Clone, Debug, Eq, Hash, PartialOrd, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Protocol {
+    TCP,
+    UDP,
+    AH,
+    ESP,
+    GRE,
+    IPV6,
+    IGMP,
+    ANY,
+    // only produced by `parse_lenient` for protocols this crate doesn't know about
+    Other(String),
+}
+
+impl TryFrom<&str> for Protocol {
+    type Error = ParseError;
+
+    fn try_from(v: &str) -> Result<Self, Self::Error> {
+        Ok(match v.to_ascii_lowercase().as_str() {
+            "tcp" => Protocol::TCP,
+            "udp" => Protocol::UDP,
+            "ah" => Protocol::AH,
+            "esp" => Protocol::ESP,
+            "gre" => Protocol::GRE,
+            "ipv6" => Protocol::IPV6,
+            "igmp" => Protocol::IGMP,
+            // ufw leaves the protocol out for rules matching any
+            "" => Protocol::ANY,
+            _ => Err(ParseError::InvalidProtocol(v.to_string()))?
+        })
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Protocol::TCP => "tcp",
+            Protocol::UDP => "udp",
+            Protocol::AH => "ah",
+            Protocol::ESP => "esp",
+            Protocol::GRE => "gre",
+            Protocol::IPV6 => "ipv6",
+            Protocol::IGMP => "igmp",
+            // ufw expects no protocol at all for rules matching any
+            Protocol::ANY => "",
+            Protocol::Other(proto) => proto,
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl Protocol {
+    pub(crate) fn lenient(value: &str) -> Protocol {
+        Protocol::try_from(value).unwrap_or_else(|_| Protocol::Other(value.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_protocol_in_any_case() {
+        let protocols = [("tcp", Protocol::TCP), ("udp", Protocol::UDP), ("ah", Protocol::AH), ("esp", Protocol::ESP),
+            ("gre", Protocol::GRE), ("ipv6", Protocol::IPV6), ("igmp", Protocol::IGMP), ("", Protocol::ANY)];
+        for (text, protocol) in protocols.iter() {
+            assert_eq!(&Protocol::try_from(*text).unwrap(), protocol);
+            assert_eq!(&Protocol::try_from(text.to_uppercase().as_str()).unwrap(), protocol);
+        }
+        assert_eq!(Protocol::try_from("Tcp").unwrap(), Protocol::TCP);
+
+        assert!(matches!(Protocol::try_from("sctp"), Err(ParseError::InvalidProtocol(protocol)) if protocol == "sctp"));
+    }
+
+    #[test]
+    fn display_round_trips_with_try_from() {
+        let protocols = [Protocol::TCP, Protocol::UDP, Protocol::AH, Protocol::ESP, Protocol::GRE, Protocol::IPV6, Protocol::IGMP];
+        for protocol in protocols.iter() {
+            assert_eq!(&Protocol::try_from(protocol.to_string().as_str()).unwrap(), protocol);
+        }
+
+        assert_eq!(Protocol::ANY.to_string(), "");
+    }
+}
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::io;
 use std::net::IpAddr;
 use std::num::ParseIntError;
@@ -6,67 +9,90 @@ use std::path::PathBuf;
 use std::process::{Command, Output};
 use std::str;
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use anyhow::{Context, Error, Result};
 use regex::{Captures, Regex};
+use serde_derive::Deserialize;
 
-use crate::{ParseError, ParseResult};
+use crate::{CommandError, CommandResult, Config, ParseError, ParseResult, Protocol};
+use crate::application::{Application, ApplicationEntry, parse_app_info, parse_app_list};
+use crate::config::UfwConf;
+use crate::numbered::{self, Line};
 use crate::ParseError::{InvalidLoggingLevel, IOError};
 
-#[derive(Clone, Copy, Debug)]
-pub enum Protocol {
-    TCP,
-    UDP,
-    AH,
-    ESP,
-    GRE,
-    IPV6,
-    IGMP,
-    ANY,
-}
-
-impl TryFrom<&str> for Protocol {
-    type Error = ParseError;
-
-    fn try_from(v: &str) -> Result<Self, Self::Error> {
-        Ok(match v {
-            "tcp" => Protocol::TCP,
-            "udp" => Protocol::UDP,
-            _ => Err(ParseError::InvalidProtocol(v.to_string()))?
-        })
-    }
-}
-
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum IpVersion {
     V4,
     V6,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct UfwAction {
     typ: RuleType,
     direction: RuleDirection,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl UfwAction {
+    pub fn typ(&self) -> &RuleType {
+        &self.typ
+    }
+
+    pub fn direction(&self) -> &RuleDirection {
+        &self.direction
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Address {
-    addr: IpAddr,
-    cidr: u8,
+    pub(crate) addr: IpAddr,
+    pub(crate) cidr: u8,
 }
 
 impl From<IpAddr> for Address {
     fn from(ip: IpAddr) -> Self {
         Address {
             addr: ip,
-            cidr: 32,
+            cidr: max_cidr(&ip),
+        }
+    }
+}
+
+fn max_cidr(addr: &IpAddr) -> u8 {
+    if addr.is_ipv6() { 128 } else { 32 }
+}
+
+impl Address {
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn cidr(&self) -> u8 {
+        self.cidr
+    }
+
+    pub fn contains(&self, other: &Address) -> bool {
+        if self.cidr > other.cidr {
+            return false;
+        }
+
+        match (self.addr, other.addr) {
+            (IpAddr::V4(network), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32u32.saturating_sub(self.cidr.into())).unwrap_or(0);
+                u32::from(network) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128u32.saturating_sub(self.cidr.into())).unwrap_or(0);
+                u128::from(network) & mask == u128::from(addr) & mask
+            }
+            _ => false,
         }
     }
 }
 
 impl ToString for Address {
     fn to_string(&self) -> String {
-        if self.cidr == 32 {
+        if self.cidr == max_cidr(&self.addr) {
             self.addr.to_string()
         } else {
             vec![self.addr.to_string(), self.cidr.to_string()].join("/")
@@ -81,387 +107,2949 @@ impl TryFrom<&str> for Address {
         Ok(match s.rfind('/') {
             None => Address::from(IpAddr::from_str(s)?),
             Some(pos) => {
+                let addr = IpAddr::from_str(&s[0..pos])?;
+                let cidr = u8::from_str(&s[pos + 1..])?;
+                if cidr > max_cidr(&addr) {
+                    Err(anyhow!("cidr must be >= 0 && <= {}", max_cidr(&addr)))?
+                }
+
                 Address {
-                    addr: IpAddr::from_str(&s[0..pos])?,
-                    cidr: u8::from_str(&s[pos + 1..])?,
+                    addr,
+                    cidr,
                 }
             }
         })
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct RuleEntry {
-    interface: Option<String>,
-    source_address: Option<Address>,
-    destination_address: Option<Address>,
-    source_port: Option<u16>,
-    destination_port: Option<u16>,
-    proto: Protocol,
-    ip_version: Option<IpVersion>,
-    number: u16,
-    action: UfwAction,
+    pub(crate) interface: Option<String>,
+    // routes can name the interface packets leave through as well, `interface` is the one they come in on then
+    pub(crate) out_interface: Option<String>,
+    pub(crate) source_address: Option<Address>,
+    pub(crate) destination_address: Option<Address>,
+    pub(crate) source_port: Option<u16>,
+    pub(crate) destination_port: Option<u16>,
+    // the last port of a range (`port 6000:6007`) starting at the port above
+    pub(crate) source_end_port: Option<u16>,
+    pub(crate) destination_end_port: Option<u16>,
+    // application profile names (`ufw allow OpenSSH`) used instead of a port
+    pub(crate) source_application: Option<String>,
+    pub(crate) destination_application: Option<String>,
+    pub(crate) proto: Protocol,
+    pub(crate) ip_version: Option<IpVersion>,
+    pub(crate) number: u16,
+    pub(crate) action: UfwAction,
+    // `ufw allow 22/tcp comment 'ssh'`, doesn't change what the rule matches
+    pub(crate) comment: Option<String>,
 }
 
 impl RuleEntry {
-    fn source_address_string(&self) -> String {
-        match &self.source_address {
-            None => {
-                "any".into()
-            }
-            Some(val) => {
-                val.to_string()
-            }
+    pub(crate) fn new(typ: RuleType, direction: RuleDirection) -> RuleEntry {
+        RuleEntry {
+            interface: None,
+            out_interface: None,
+            source_address: None,
+            destination_address: None,
+            source_port: None,
+            destination_port: None,
+            source_end_port: None,
+            destination_end_port: None,
+            source_application: None,
+            destination_application: None,
+            proto: Protocol::ANY,
+            ip_version: None,
+            number: 0,
+            action: UfwAction {
+                typ,
+                direction,
+            },
+            comment: None,
         }
     }
-}
 
-impl ToString for RuleEntry {
-    fn to_string(&self) -> String {
-        unimplemented!()
+    fn allow_tcp(port: u16) -> RuleEntry {
+        RuleEntry {
+            destination_port: Some(port),
+            proto: Protocol::TCP,
+            ..RuleEntry::new(RuleType::ALLOW, RuleDirection::IN)
+        }
     }
-}
-
-#[derive(Debug)]
-pub enum RuleDirection {
-    IN,
-    OUT,
-    FWD,
-}
-
-#[derive(Debug)]
-pub enum RuleDirectionDefaults {
-    INCOMING,
-    OUTGOING,
-    ROUTED,
-}
-
-#[derive(Debug)]
-pub enum RuleType {
-    ALLOW,
-    DENY,
-    REJECT,
-    LIMIT,
-}
-
-impl TryFrom<&str> for RuleDirectionDefaults {
-    type Error = ParseError;
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Ok(match value.to_ascii_lowercase().as_str() {
-            "incoming" => RuleDirectionDefaults::INCOMING,
-            "outgoing" => RuleDirectionDefaults::OUTGOING,
-            "routed" => RuleDirectionDefaults::ROUTED,
-            &_ => Err(ParseError::WrongRuleDirection(value.to_string()))?,
-        })
+    pub fn allow_ssh() -> RuleEntry {
+        RuleEntry::allow_tcp(22)
     }
-}
 
-impl TryFrom<&str> for RuleType {
-    type Error = ParseError;
-
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Ok(match value.to_ascii_lowercase().as_str() {
-            "allow" => RuleType::ALLOW,
-            "deny" => RuleType::DENY,
-            "reject" => RuleType::REJECT,
-            "limit" => RuleType::LIMIT,
-            &_ => Err(ParseError::WrongRuleType(value.to_string()))?,
-        })
+    pub fn allow_http() -> RuleEntry {
+        RuleEntry::allow_tcp(80)
     }
-}
 
-impl TryFrom<&str> for RuleDirection {
-    type Error = ParseError;
-
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Ok(match value.to_ascii_lowercase().as_str() {
-            "fwd" => RuleDirection::FWD,
-            "out" => RuleDirection::OUT,
-            "in" => RuleDirection::IN,
-            &_ => Err(ParseError::WrongRuleDirection(value.to_string()))?,
-        })
+    pub fn allow_https() -> RuleEntry {
+        RuleEntry::allow_tcp(443)
     }
-}
-
-#[derive(Debug)]
-pub enum ReportFormats {
-    Raw,
-    Builtins,
-    BeforeRules,
-    UserRules,
-    AfterRules,
-    LoggingRules,
-    Listening,
-    Added,
-}
 
-#[derive(Debug)]
-pub enum LoggingLevel {
-    Off,
-    Low,
-    Medium,
-    High,
-    Full,
-}
+    pub fn interface(&self) -> Option<&str> {
+        self.interface.as_deref()
+    }
 
-impl TryFrom<(&str, &str)> for LoggingLevel {
-    type Error = ParseError;
+    pub fn out_interface(&self) -> Option<&str> {
+        self.out_interface.as_deref()
+    }
 
-    fn try_from(value: (&str, &str)) -> Result<Self, Self::Error> {
-        let (onoff, level) = value;
+    pub fn source_address(&self) -> Option<&Address> {
+        self.source_address.as_ref()
+    }
 
-        match onoff {
-            "on" => {
-                match level {
-                    "low" => Ok(LoggingLevel::Low),
-                    "medium" => Ok(LoggingLevel::Medium),
-                    "high" => Ok(LoggingLevel::High),
-                    "full" => Ok(LoggingLevel::Full),
-                    &_ => Err(ParseError::InvalidLoggingLevel(level.to_string())),
-                }
-            }
-            "off" => {
-                Ok(LoggingLevel::Off)
-            }
-            &_ => Err(ParseError::InvalidLoggingLevel(onoff.to_string())),
-        }
+    pub fn destination_address(&self) -> Option<&Address> {
+        self.destination_address.as_ref()
     }
-}
 
-#[derive(Debug)]
-pub struct Ufw {
-    enabled: bool,
-    logging: LoggingLevel,
-    entries: Vec<(RuleEntry, bool)>,
-}
+    pub fn source_port(&self) -> Option<u16> {
+        self.source_port
+    }
 
-// keep old rules until submitting
-impl Ufw {
-    pub fn add_rule(&mut self, entry: RuleEntry) {
-        self.entries.push((entry, true))
+    /// ```
+    /// use ufw_auto_block::{parse_rule_spec, Protocol};
+    ///
+    /// let rule = parse_rule_spec("allow in on eth0 to any port 22 proto tcp").unwrap();
+    /// assert_eq!(rule.destination_port(), Some(22));
+    /// assert_eq!(rule.proto(), &Protocol::TCP);
+    /// ```
+    pub fn destination_port(&self) -> Option<u16> {
+        self.destination_port
     }
 
-    pub fn delete_rule(&mut self, entry_index: u16) -> Option<(RuleEntry, bool)> {
-        if entry_index > self.entries.len() as u16 {
-            None
-        } else {
-            Some(self.entries.remove(entry_index.into()))
-        }
+    pub fn source_end_port(&self) -> Option<u16> {
+        self.source_end_port
     }
 
-    pub fn submit(self) -> io::Result<Vec<Output>> {
-        self.entries
-            .iter()
-            .enumerate()
-            .filter_map(|(index, (entry, commit_status))|
-                if !commit_status {
-                    Some(UfwCommand::new().exec(vec!["insert", &index.to_string(), &entry.to_string()]))
-                } else {
-                    None
-                })
-            .collect()
+    pub fn destination_end_port(&self) -> Option<u16> {
+        self.destination_end_port
     }
-}
 
-#[derive(Debug)]
-pub(crate) struct UfwPort {
-    pub(crate) number: u16,
-    pub(crate) end_number: Option<u16>,
-    pub(crate) protocols: Vec<ParseResult<Protocol>>,
-}
+    pub fn source_application(&self) -> Option<&str> {
+        self.source_application.as_deref()
+    }
 
-pub struct UfwCommand {
-    executable: PathBuf,
-}
+    pub fn destination_application(&self) -> Option<&str> {
+        self.destination_application.as_deref()
+    }
 
-pub type UfwCommandOutput = Output;
+    pub fn proto(&self) -> &Protocol {
+        &self.proto
+    }
 
-struct LineNumberRuleEntry {}
+    pub fn ip_version(&self) -> Option<&IpVersion> {
+        self.ip_version.as_ref()
+    }
 
-struct LineNumberField {
-    name: String,
-    value: Vec<ParseResult<UfwPort>>, // TOOD correct type?
-}
+    // the position in `ufw status numbered`, 0 for rules which weren't read from ufw
+    pub fn number(&self) -> u16 {
+        self.number
+    }
 
-impl UfwCommand {
-    pub fn new() -> UfwCommand {
-        UfwCommand {
-            executable: PathBuf::from("/usr/bin/ufw")
-        }
+    pub fn action(&self) -> &UfwAction {
+        &self.action
     }
 
-    pub fn with_executable<P: Into<PathBuf>>(&mut self, executable_path: P) -> &mut UfwCommand {
-        self.executable = executable_path.into();
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
 
-        self
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        match self.action.typ {
+            RuleType::LIMIT => Some(RateLimit::default()),
+            _ => None,
+        }
     }
 
-    fn parse_action(arguments: Vec<&str>) -> ParseResult<UfwAction> {
-        let rule_type = arguments.get(0)
-            .ok_or(ParseError::WrongRuleType(String::from("couldn't get rule type")))?.to_owned();
-        let rule_direction = arguments.get(1)
-            .ok_or(ParseError::WrongRuleType(String::from("couldn't get rule direction")))?.to_owned();
+    // whether every packet matched by `other` is matched by this rule as well, `None` fields match anything
+    pub fn covers(&self, other: &RuleEntry) -> bool {
+        let address_covers = |this: &Option<Address>, other: &Option<Address>| match (this, other) {
+            (None, _) => true,
+            (Some(this), Some(other)) => this.contains(other),
+            (Some(_), None) => false,
+        };
+        // a single port is a range ending at itself
+        let port_covers = |this: (Option<u16>, Option<u16>), other: (Option<u16>, Option<u16>)| match (this, other) {
+            ((None, _), _) => true,
+            ((Some(start), end), (Some(other_start), other_end)) => {
+                start <= other_start && other_end.unwrap_or(other_start) <= end.unwrap_or(start)
+            }
+            ((Some(_), _), (None, _)) => false,
+        };
+        let application_covers = |this: &Option<String>, other: &Option<String>| this.is_none() || this == other;
 
-        Ok(UfwAction {
-            typ: RuleType::try_from(rule_type)?,
-            direction: RuleDirection::try_from(rule_direction)?,
-        })
+        self.action.direction == other.action.direction
+            && (self.interface.is_none() || self.interface == other.interface)
+            && (self.out_interface.is_none() || self.out_interface == other.out_interface)
+            && (self.proto == Protocol::ANY || self.proto == other.proto)
+            && address_covers(&self.source_address, &other.source_address)
+            && address_covers(&self.destination_address, &other.destination_address)
+            && port_covers((self.source_port, self.source_end_port), (other.source_port, other.source_end_port))
+            && port_covers((self.destination_port, self.destination_end_port), (other.destination_port, other.destination_end_port))
+            && application_covers(&self.source_application, &other.source_application)
+            && application_covers(&self.destination_application, &other.destination_application)
+            && match (&self.ip_version, &other.ip_version) {
+            (Some(this), Some(other)) => this == other,
+            _ => true,
+        }
     }
 
-    fn parse_stdout(o: Vec<u8>) -> io::Result<String> {
-        match str::from_utf8(&o) {
-            Ok(val) => {
-                Ok(val.to_string())
+    fn source_address_string(&self) -> String {
+        match &self.source_address {
+            None => {
+                "any".into()
             }
-            Err(err) => {
-                Err(io::Error::new(io::ErrorKind::Other, err.to_string()))
+            Some(val) => {
+                val.to_string()
             }
         }
     }
 
-    pub fn version(&self) -> io::Result<String> {
-        let output = self.exec(vec!["version"])?;
-
-        if output.status.success() {
-            let text = UfwCommand::parse_stdout(output.stdout)?;
-            match Regex::new(r"ufw (\d+\.\d+(?:\.\d+)?)")
-                .unwrap()
-                .captures(&text) {
-                None => {
-                    let error_message = format!("Couldn't find a valid ufw version in {}", text);
-                    Err(io::Error::new(io::ErrorKind::InvalidInput, error_message))
-                }
-                Some(captures) => {
-                    match captures.get(1) {
-                        None => {
-                            let error_message = format!("Couldn't find a valid ufw version in {}", text);
-                            Err(io::Error::new(io::ErrorKind::InvalidInput, error_message))
-                        }
-                        Some(capture) => {
-                            Ok(capture.as_str().to_string())
-                        }
-                    }
-                }
+    fn destination_address_string(&self) -> String {
+        match &self.destination_address {
+            None => {
+                "any".into()
+            }
+            Some(val) => {
+                val.to_string()
             }
-        } else {
-            Err(io::Error::new(io::ErrorKind::Other, format!("ufw execution unsuccessful: {:?}", str::from_utf8(&output.stderr))))?
         }
     }
 
-    pub fn info(&self) -> ParseResult<(bool, LoggingLevel)> {
-        let output = self.exec(vec!["status", "verbose"]).map_err(|e| IOError(e.to_string()))?;
+    fn proto_argument(&self) -> Option<String> {
+        match &self.proto {
+            Protocol::ANY => None,
+            proto => Some(proto.to_string()),
+        }
+    }
 
-        if output.status.success() {
-            let text = match str::from_utf8(&*output.stdout) {
-                Ok(val) => {
-                    Ok(val)
-                }
-                Err(err) => {
-                    Err(IOError(err.to_string()))
-                }
-            }?;
+    fn port_argument(port: u16, end_port: Option<u16>) -> String {
+        match end_port {
+            Some(end_port) => format!("{}:{}", port, end_port),
+            None => port.to_string(),
+        }
+    }
 
-            let enabled = match Regex::new(r"Status:\s*((:?in)?active)")
-                .unwrap()
-                .captures(text) {
-                None => {
-                    let error_message = format!("Couldn't find a valid ufw version in {}", text);
-                    Err(IOError(error_message))
-                }
-                Some(captures) => {
-                    match captures.get(1) {
-                        None => {
-                            let error_message = format!("Couldn't find a valid logging level in: {}", text);
-                            Err(IOError(error_message))
-                        }
-                        Some(capture) => {
-                            Ok(capture.as_str().to_string())
-                        }
-                    }
-                }
-            }? == "active";
-            let logging_level = match Regex::new(r"Logging:\s*(on|off)\s*(:?\((\w+)\))?")
-                .unwrap()
-                .captures(text) {
-                None => {
-                    let error_message = format!("Couldn't find a valid logging level {}", text);
-                    Err(InvalidLoggingLevel(text.to_string()))
-                }
-                Some(captures) => {
-                    let (state, level) = (captures.get(1), captures.get(2));
-                    if state.is_some() && level.is_some() {
-                        let level = level.unwrap().as_str().replace("(", "").replace(")", "");
-                        LoggingLevel::try_from((state.unwrap().as_str(), level.as_str()))
-                    } else {
-                        let message = format!("Invalid logging level ({}) found.\
-                            Valid logging levels are: `low`, `medium`, `high`.\
-                            \nBeware that ufw has no checks on what you're setting the value to and allows anything.",
-                                              text.to_string());
-                        Err(InvalidLoggingLevel(message))
-                    }
-                }
-            }?;
+    // the rule in ufw's full syntax, every keyword is a separate argument for the ufw executable:
+    // [route] allow|deny|reject|limit [in|out [on INTERFACE]] [proto PROTOCOL] [from ADDRESS [port PORT]] [to ADDRESS [port PORT]]
+    pub(crate) fn arguments(&self) -> Vec<String> {
+        let mut arguments = vec![];
 
-            Ok((enabled, logging_level))
+        if self.action.direction == RuleDirection::FWD {
+            arguments.push("route".to_string());
+            arguments.push(self.action.typ.to_string());
+            if let Some(interface) = &self.interface {
+                arguments.extend(vec!["in".to_string(), "on".to_string(), interface.to_owned()]);
+            }
+            if let Some(interface) = &self.out_interface {
+                arguments.extend(vec!["out".to_string(), "on".to_string(), interface.to_owned()]);
+            }
         } else {
-            Err(IOError(format!("ufw execution unsuccessful: {:?}", str::from_utf8(&output.stderr))))
+            arguments.push(self.action.typ.to_string());
+            arguments.push(self.action.direction.to_string());
+            if let Some(interface) = &self.interface {
+                arguments.extend(vec!["on".to_string(), interface.to_owned()]);
+            }
+        }
+
+        if let Some(proto) = self.proto_argument() {
+            arguments.extend(vec!["proto".to_string(), proto]);
+        }
+
+        // `from any` and `to any` are ufw's defaults, at least one side is needed for a valid rule though
+        let has_source = self.source_address.is_some() || self.source_port.is_some() || self.source_application.is_some();
+        let has_destination = self.destination_address.is_some() || self.destination_port.is_some() || self.destination_application.is_some();
+
+        if has_source {
+            arguments.extend(vec!["from".to_string(), self.source_address_string()]);
+        }
+        if let Some(port) = self.source_port {
+            arguments.extend(vec!["port".to_string(), RuleEntry::port_argument(port, self.source_end_port)]);
+        }
+        if let Some(application) = &self.source_application {
+            arguments.extend(vec!["app".to_string(), application.to_owned()]);
+        }
+
+        if has_destination || !has_source {
+            arguments.extend(vec!["to".to_string(), self.destination_address_string()]);
+        }
+        if let Some(port) = self.destination_port {
+            arguments.extend(vec!["port".to_string(), RuleEntry::port_argument(port, self.destination_end_port)]);
+        }
+        if let Some(application) = &self.destination_application {
+            arguments.extend(vec!["app".to_string(), application.to_owned()]);
+        }
+
+        if let Some(comment) = &self.comment {
+            arguments.extend(vec!["comment".to_string(), comment.to_owned()]);
+        }
+
+        arguments
+    }
+}
+
+impl RuleEntry {
+    // a row of `ufw status numbered`, e.g. `[ 1] 22/tcp  ALLOW IN  Anywhere`, parsed like `numbered::parse` does
+    pub(crate) fn from_numbered_row(row: &str) -> ParseResult<RuleEntry> {
+        let line = numbered::parse(row).map_err(|e| ParseError::InvalidStatusRow(format!("{}: {:#}", row.trim(), e)))?;
+
+        RuleEntry::try_from(&line)
+    }
+}
+
+impl RuleEntry {
+    // expands profile names (`ufw allow OpenSSH`) into one rule per port and protocol of the profile,
+    // `None` if a referenced profile isn't part of `applications`
+    pub fn resolve_applications(&self, applications: &[Application]) -> Option<Vec<RuleEntry>> {
+        let find = |name: &str| applications
+            .iter()
+            .flat_map(|application| application.entries.iter().flatten())
+            .find(|entry| entry.name.eq_ignore_ascii_case(name));
+        // the profile's ports as `ApplicationEntry::to_rules` expands them, always as destination ports
+        let expand = |entry: &ApplicationEntry| entry.to_rules(self.action.typ, self.action.direction);
+
+        let mut rules = vec![self.clone()];
+        if let Some(name) = &self.destination_application {
+            let expanded = expand(find(name)?);
+            rules = rules
+                .iter()
+                .flat_map(|rule| expanded.iter().map(move |port| RuleEntry {
+                    destination_port: port.destination_port,
+                    destination_end_port: port.destination_end_port,
+                    destination_application: None,
+                    proto: port.proto.clone(),
+                    ..rule.clone()
+                }))
+                .collect();
+        }
+        if let Some(name) = &self.source_application {
+            let expanded = expand(find(name)?);
+            rules = rules
+                .iter()
+                .flat_map(|rule| expanded.iter().map(move |port| RuleEntry {
+                    source_port: port.destination_port,
+                    source_end_port: port.destination_end_port,
+                    source_application: None,
+                    proto: port.proto.clone(),
+                    ..rule.clone()
+                }))
+                .collect();
+        }
+
+        Some(rules)
+    }
+}
+
+fn parse_spec_address(spec: &str, address: Option<&str>) -> ParseResult<Option<Address>> {
+    match address {
+        Some("any") => Ok(None),
+        Some(address) => Address::try_from(address)
+            .map(Some)
+            .map_err(|e| ParseError::InvalidRuleSpec(format!("{}: {}", spec, e))),
+        None => Err(ParseError::InvalidRuleSpec(spec.to_string())),
+    }
+}
+
+// a single port or a range like `6000:6007`
+fn parse_spec_port(spec: &str, port: Option<&str>) -> ParseResult<(u16, Option<u16>)> {
+    let port = port.ok_or(ParseError::InvalidRuleSpec(spec.to_string()))?;
+
+    match port.split_once(':') {
+        Some((start, end)) => {
+            let (start, end) = (start.parse::<u16>()?, end.parse::<u16>()?);
+            // like ufw, a range has to span at least two ports
+            if end <= start {
+                Err(ParseError::InvalidPortRange(format!("{}:{}", start, end)))?
+            }
+
+            Ok((start, Some(end)))
+        }
+        None => Ok((port.parse::<u16>()?, None)),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RuleEntryBuilder {
+    typ: Option<RuleType>,
+    direction: Option<RuleDirection>,
+    interface: Option<String>,
+    out_interface: Option<String>,
+    source_address: Option<Address>,
+    destination_address: Option<Address>,
+    source_port: Option<u16>,
+    destination_port: Option<u16>,
+    source_end_port: Option<u16>,
+    destination_end_port: Option<u16>,
+    source_application: Option<String>,
+    destination_application: Option<String>,
+    proto: Option<Protocol>,
+    comment: Option<String>,
+}
+
+impl RuleEntryBuilder {
+    pub fn new() -> RuleEntryBuilder {
+        RuleEntryBuilder::default()
+    }
+
+    pub fn rule_type(self, typ: RuleType) -> RuleEntryBuilder {
+        RuleEntryBuilder { typ: Some(typ), ..self }
+    }
+
+    pub fn allow(self) -> RuleEntryBuilder {
+        self.rule_type(RuleType::ALLOW)
+    }
+
+    pub fn deny(self) -> RuleEntryBuilder {
+        self.rule_type(RuleType::DENY)
+    }
+
+    pub fn reject(self) -> RuleEntryBuilder {
+        self.rule_type(RuleType::REJECT)
+    }
+
+    pub fn limit(self) -> RuleEntryBuilder {
+        self.rule_type(RuleType::LIMIT)
+    }
+
+    pub fn direction(self, direction: RuleDirection) -> RuleEntryBuilder {
+        RuleEntryBuilder { direction: Some(direction), ..self }
+    }
+
+    pub fn direction_in(self) -> RuleEntryBuilder {
+        self.direction(RuleDirection::IN)
+    }
+
+    pub fn direction_out(self) -> RuleEntryBuilder {
+        self.direction(RuleDirection::OUT)
+    }
+
+    pub fn route(self) -> RuleEntryBuilder {
+        self.direction(RuleDirection::FWD)
+    }
+
+    pub fn on<S: ToString>(self, interface: S) -> RuleEntryBuilder {
+        RuleEntryBuilder { interface: Some(interface.to_string()), ..self }
+    }
+
+    // the interface routed packets leave through, `on` is the one they come in on
+    pub fn out_on<S: ToString>(self, interface: S) -> RuleEntryBuilder {
+        RuleEntryBuilder { out_interface: Some(interface.to_string()), ..self }
+    }
+
+    pub fn from_any(self) -> RuleEntryBuilder {
+        RuleEntryBuilder { source_address: None, ..self }
+    }
+
+    pub fn from_address(self, address: Address) -> RuleEntryBuilder {
+        RuleEntryBuilder { source_address: Some(address), ..self }
+    }
+
+    pub fn from_port(self, port: u16) -> RuleEntryBuilder {
+        RuleEntryBuilder { source_port: Some(port), source_end_port: None, ..self }
+    }
+
+    pub fn from_port_range(self, start: u16, end: u16) -> RuleEntryBuilder {
+        RuleEntryBuilder { source_port: Some(start), source_end_port: Some(end), ..self }
+    }
+
+    pub fn from_app<S: ToString>(self, application: S) -> RuleEntryBuilder {
+        RuleEntryBuilder { source_application: Some(application.to_string()), ..self }
+    }
+
+    pub fn to_any(self) -> RuleEntryBuilder {
+        RuleEntryBuilder { destination_address: None, ..self }
+    }
+
+    pub fn to_address(self, address: Address) -> RuleEntryBuilder {
+        RuleEntryBuilder { destination_address: Some(address), ..self }
+    }
+
+    pub fn to_port(self, port: u16) -> RuleEntryBuilder {
+        RuleEntryBuilder { destination_port: Some(port), destination_end_port: None, ..self }
+    }
+
+    pub fn to_port_range(self, start: u16, end: u16) -> RuleEntryBuilder {
+        RuleEntryBuilder { destination_port: Some(start), destination_end_port: Some(end), ..self }
+    }
+
+    pub fn to_app<S: ToString>(self, application: S) -> RuleEntryBuilder {
+        RuleEntryBuilder { destination_application: Some(application.to_string()), ..self }
+    }
+
+    pub fn proto(self, proto: Protocol) -> RuleEntryBuilder {
+        RuleEntryBuilder { proto: Some(proto), ..self }
+    }
+
+    pub fn comment<S: ToString>(self, comment: S) -> RuleEntryBuilder {
+        RuleEntryBuilder { comment: Some(comment.to_string()), ..self }
+    }
+
+    // rejects what ufw itself refuses to add
+    pub fn build(self) -> ParseResult<RuleEntry> {
+        let invalid = |message: &str| Err(ParseError::InvalidRuleSpec(message.to_string()));
+
+        let typ = match self.typ {
+            Some(typ) => typ,
+            None => return invalid("a rule needs one of allow, deny, reject or limit"),
+        };
+        let proto = self.proto.clone().unwrap_or(Protocol::ANY);
+        let has_port = self.source_port.is_some() || self.destination_port.is_some();
+        let has_application = self.source_application.is_some() || self.destination_application.is_some();
+
+        // ports only exist for tcp and udp, `any` means both
+        if has_port && !matches!(proto, Protocol::TCP | Protocol::UDP | Protocol::ANY) {
+            return invalid("ports can only be used with tcp or udp");
+        }
+        let ranges = [(self.source_port, self.source_end_port), (self.destination_port, self.destination_end_port)];
+        if ranges.iter().any(|range| matches!(range, (Some(start), Some(end)) if end <= start)) {
+            return invalid("a port range has to end after its first port");
+        }
+        // unlike single ports, ranges need the protocol spelled out
+        let has_range = ranges.iter().any(|(_, end)| end.is_some());
+        if has_range && !matches!(proto, Protocol::TCP | Protocol::UDP) {
+            return invalid("port ranges can only be used with either tcp or udp");
+        }
+        // the profile decides about ports and protocols of an application
+        if has_application && self.proto.is_some() {
+            return invalid("applications can't be combined with a protocol");
+        }
+        if (self.source_application.is_some() && self.source_port.is_some())
+            || (self.destination_application.is_some() && self.destination_port.is_some()) {
+            return invalid("applications can't be combined with a port");
+        }
+        if self.out_interface.is_some() && self.direction != Some(RuleDirection::FWD) {
+            return invalid("only routes can have an outgoing interface besides the incoming one");
+        }
+        if let (Some(source), Some(destination)) = (&self.source_address, &self.destination_address) {
+            if source.addr.is_ipv6() != destination.addr.is_ipv6() {
+                return invalid("source and destination must be of the same ip version");
+            }
+        }
+
+        Ok(RuleEntry {
+            interface: self.interface,
+            out_interface: self.out_interface,
+            source_address: self.source_address,
+            destination_address: self.destination_address,
+            source_port: self.source_port,
+            destination_port: self.destination_port,
+            source_end_port: self.source_end_port,
+            destination_end_port: self.destination_end_port,
+            source_application: self.source_application,
+            destination_application: self.destination_application,
+            proto,
+            comment: self.comment,
+            ..RuleEntry::new(typ, self.direction.unwrap_or(RuleDirection::IN))
+        })
+    }
+}
+
+// splits a rule like a shell would, the reverse of `quote_argument`. quoted arguments keep all their spaces
+fn split_arguments(spec: &str) -> ParseResult<Vec<String>> {
+    let mut arguments = vec![];
+    let mut current: Option<String> = None;
+    let mut chars = spec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                let argument = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some(quote) if quote == c => break,
+                        Some('\\') if c == '"' => argument.extend(chars.next()),
+                        Some(other) => argument.push(other),
+                        None => Err(ParseError::InvalidRuleSpec(format!("{}: unterminated quote", spec)))?,
+                    }
+                }
+            }
+            '\\' => current.get_or_insert_with(String::new).extend(chars.next()),
+            c if c.is_whitespace() => arguments.extend(current.take()),
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    arguments.extend(current);
+
+    Ok(arguments)
+}
+
+// the command side of `from_numbered_row`, parses the arguments of e.g. `ufw allow in on eth0 from any to any port 22 proto tcp`
+pub fn parse_rule_spec(spec: &str) -> ParseResult<RuleEntry> {
+    let invalid = |reason: &str| ParseError::InvalidRuleSpec(format!("{}: {}", spec, reason));
+    let arguments = split_arguments(spec)?;
+    let mut tokens = arguments.iter().map(String::as_str).peekable();
+
+    let route = tokens.peek() == Some(&"route");
+    if route {
+        tokens.next();
+    }
+    let typ = RuleType::try_from(tokens.next().ok_or(ParseError::InvalidRuleSpec(spec.to_string()))?)?;
+
+    let mut entry = RuleEntry::new(typ, if route { RuleDirection::FWD } else { RuleDirection::IN });
+    let mut full_syntax = false;
+    let mut has_target = false;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "in" | "out" => {
+                let direction = RuleDirection::try_from(token)?;
+                if !route {
+                    if entry.interface.is_some() || full_syntax {
+                        Err(invalid("the direction has to come first and only once"))?
+                    }
+                    entry.action.direction = direction;
+                }
+                if tokens.peek() == Some(&"on") {
+                    tokens.next();
+                    let interface = Some(tokens.next().ok_or_else(|| invalid("missing interface"))?.to_string());
+                    let slot = if route && direction == RuleDirection::OUT { &mut entry.out_interface } else { &mut entry.interface };
+                    if slot.is_some() {
+                        Err(invalid("an interface is given twice"))?
+                    }
+                    *slot = interface;
+                    has_target = true;
+                }
+            }
+            // RuleEntry has nothing to keep the logging of a rule in, it'd be lost when submitting the rule again
+            "log" | "log-all" => Err(invalid("logging rules aren't supported"))?,
+            "proto" => {
+                let proto = tokens.next().ok_or_else(|| invalid("missing protocol"))?;
+                entry.proto = Protocol::try_from(proto)?;
+                full_syntax = true;
+            }
+            "from" | "to" => {
+                let address = parse_spec_address(spec, tokens.next())?;
+                let ((port, end_port), application) = match tokens.peek() {
+                    Some(&"port") => {
+                        tokens.next();
+                        let (port, end_port) = parse_spec_port(spec, tokens.next())?;
+                        ((Some(port), end_port), None)
+                    }
+                    Some(&"app") => {
+                        tokens.next();
+                        ((None, None), Some(tokens.next().ok_or_else(|| invalid("missing application"))?.to_string()))
+                    }
+                    _ => ((None, None), None),
+                };
+
+                if token == "from" {
+                    entry.source_address = address;
+                    entry.source_port = port;
+                    entry.source_end_port = end_port;
+                    entry.source_application = application;
+                } else {
+                    entry.destination_address = address;
+                    entry.destination_port = port;
+                    entry.destination_end_port = end_port;
+                    entry.destination_application = application;
+                }
+                full_syntax = true;
+                has_target = true;
+            }
+            // always the last argument, quoting keeps its spaces
+            "comment" => {
+                let comment = tokens.by_ref().collect::<Vec<&str>>().join(" ");
+                if comment.is_empty() {
+                    Err(invalid("missing comment"))?
+                }
+                entry.comment = Some(comment);
+            }
+            // simple syntax, `allow 22/tcp` for a port or `allow OpenSSH` for an application profile
+            _ if !full_syntax && entry.destination_port.is_none() && entry.destination_application.is_none() && matches!(tokens.peek(), None | Some(&"comment")) => {
+                if token.starts_with(|c: char| c.is_ascii_digit()) {
+                    let mut port_protocol = token.split('/');
+                    let (port, end_port) = parse_spec_port(spec, port_protocol.next())?;
+                    entry.destination_port = Some(port);
+                    entry.destination_end_port = end_port;
+                    if let Some(proto) = port_protocol.next() {
+                        entry.proto = Protocol::try_from(proto)?;
+                    }
+                } else {
+                    entry.destination_application = Some(token.to_string());
+                }
+                has_target = true;
+            }
+            _ => Err(invalid(&format!("unexpected `{}`", token)))?
+        }
+    }
+
+    // ufw needs to know what a rule applies to, `ufw allow` on its own is an error
+    if !has_target {
+        Err(invalid("missing a port, application, address or interface"))?
+    }
+
+    let has_port = entry.source_port.is_some() || entry.destination_port.is_some();
+    if has_port && !matches!(entry.proto, Protocol::TCP | Protocol::UDP | Protocol::ANY) {
+        Err(invalid("ports can only be used with tcp or udp"))?
+    }
+    let has_range = entry.source_end_port.is_some() || entry.destination_end_port.is_some();
+    if has_range && !matches!(entry.proto, Protocol::TCP | Protocol::UDP) {
+        Err(invalid("port ranges can only be used with either tcp or udp"))?
+    }
+    let has_application = entry.source_application.is_some() || entry.destination_application.is_some();
+    if has_application && entry.proto != Protocol::ANY {
+        Err(invalid("applications bring their own protocols"))?
+    }
+
+    let addresses: Vec<&Address> = [&entry.source_address, &entry.destination_address].iter().copied().flatten().collect();
+    if addresses.iter().any(|a| a.addr.is_ipv4()) && addresses.iter().any(|a| a.addr.is_ipv6()) {
+        Err(invalid("can't mix IPv4 and IPv6 addresses"))?
+    }
+    entry.ip_version = addresses.first().map(|a| if a.addr.is_ipv6() { IpVersion::V6 } else { IpVersion::V4 });
+
+    Ok(entry)
+}
+
+// rules are equal when ufw is given the same command for them, their position doesn't matter.
+// neither does the ip version, ufw derives it from the addresses (and adds both variants without any)
+impl PartialEq for RuleEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.arguments() == other.arguments()
+    }
+}
+
+impl Eq for RuleEntry {}
+
+impl Hash for RuleEntry {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.arguments().hash(state)
+    }
+}
+
+impl ToString for RuleEntry {
+    fn to_string(&self) -> String {
+        self.arguments()
+            .into_iter()
+            .map(quote_argument)
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+// arguments like comments or profile names (`Nginx Full`) can contain spaces or anything else a shell interprets.
+// they're single quoted then, a quote within them closes the quoting for an escaped one (`'it'\''s'`)
+pub(crate) fn quote_argument(argument: String) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:,@%+=".contains(c);
+
+    if argument.is_empty() || !argument.chars().all(safe) {
+        format!("'{}'", argument.replace('\'', "'\\''"))
+    } else {
+        argument
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RuleDirection {
+    IN,
+    OUT,
+    FWD,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RuleDirectionDefaults {
+    INCOMING,
+    OUTGOING,
+    ROUTED,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleType {
+    ALLOW,
+    DENY,
+    REJECT,
+    LIMIT,
+}
+
+// ufw doesn't print the rate of `limit` rules, it's hardcoded to deny an address after 6 connections within 30 seconds
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    pub(crate) hits: u32,
+    pub(crate) seconds: u32,
+}
+
+impl RateLimit {
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    pub fn seconds(&self) -> u32 {
+        self.seconds
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            hits: 6,
+            seconds: 30,
+        }
+    }
+}
+
+impl TryFrom<&str> for RuleDirectionDefaults {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "incoming" => RuleDirectionDefaults::INCOMING,
+            "outgoing" => RuleDirectionDefaults::OUTGOING,
+            "routed" => RuleDirectionDefaults::ROUTED,
+            &_ => Err(ParseError::WrongRuleDirection(value.to_string()))?,
+        })
+    }
+}
+
+impl TryFrom<&str> for RuleType {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "allow" => RuleType::ALLOW,
+            "deny" => RuleType::DENY,
+            "reject" => RuleType::REJECT,
+            "limit" => RuleType::LIMIT,
+            &_ => Err(ParseError::WrongRuleType(value.to_string()))?,
+        })
+    }
+}
+
+impl TryFrom<&str> for RuleDirection {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "fwd" => RuleDirection::FWD,
+            "out" => RuleDirection::OUT,
+            "in" => RuleDirection::IN,
+            &_ => Err(ParseError::WrongRuleDirection(value.to_string()))?,
+        })
+    }
+}
+
+// ufw prints `ALLOW IN` in its status output but only accepts `allow in` as arguments,
+// so parsing is case insensitive while formatting always emits the lowercase CLI form
+impl fmt::Display for RuleDirectionDefaults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RuleDirectionDefaults::INCOMING => "incoming",
+            RuleDirectionDefaults::OUTGOING => "outgoing",
+            RuleDirectionDefaults::ROUTED => "routed",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for RuleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RuleType::ALLOW => "allow",
+            RuleType::DENY => "deny",
+            RuleType::REJECT => "reject",
+            RuleType::LIMIT => "limit",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for RuleDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RuleDirection::IN => "in",
+            RuleDirection::OUT => "out",
+            RuleDirection::FWD => "fwd",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+// a single `deny (incoming)` entry of the `Default:` line, the label is kept even if parsing the entry failed.
+// the policy is `None` for `disabled (routed)`, ufw doesn't filter forwarded packets at all then
+#[derive(Debug)]
+pub struct DefaultPolicy {
+    label: String,
+    direction: ParseResult<RuleDirectionDefaults>,
+    policy: ParseResult<Option<RuleType>>,
+}
+
+impl DefaultPolicy {
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn direction(&self) -> &ParseResult<RuleDirectionDefaults> {
+        &self.direction
+    }
+
+    pub fn policy(&self) -> &ParseResult<Option<RuleType>> {
+        &self.policy
+    }
+}
+
+// the entries of the `Default:` line in the order ufw printed them
+#[derive(Debug)]
+pub struct DefaultPolicies {
+    policies: Vec<DefaultPolicy>,
+}
+
+impl DefaultPolicies {
+    pub fn get(&self, direction: RuleDirectionDefaults) -> Option<&ParseResult<Option<RuleType>>> {
+        let label = direction.to_string();
+
+        self.policies
+            .iter()
+            .find(|policy| policy.label == label)
+            .map(|policy| &policy.policy)
+    }
+
+    pub fn incoming(&self) -> Option<&ParseResult<Option<RuleType>>> {
+        self.get(RuleDirectionDefaults::INCOMING)
+    }
+
+    pub fn outgoing(&self) -> Option<&ParseResult<Option<RuleType>>> {
+        self.get(RuleDirectionDefaults::OUTGOING)
+    }
+
+    pub fn routed(&self) -> Option<&ParseResult<Option<RuleType>>> {
+        self.get(RuleDirectionDefaults::ROUTED)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&DefaultPolicy> {
+        self.policies.iter()
+    }
+}
+
+#[derive(Debug)]
+pub enum ReportFormats {
+    Raw,
+    Builtins,
+    BeforeRules,
+    UserRules,
+    AfterRules,
+    LoggingRules,
+    Listening,
+    Added,
+}
+
+impl TryFrom<&str> for ReportFormats {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "raw" => ReportFormats::Raw,
+            "builtins" => ReportFormats::Builtins,
+            "before-rules" => ReportFormats::BeforeRules,
+            "user-rules" => ReportFormats::UserRules,
+            "after-rules" => ReportFormats::AfterRules,
+            "logging-rules" => ReportFormats::LoggingRules,
+            "listening" => ReportFormats::Listening,
+            "added" => ReportFormats::Added,
+            &_ => Err(ParseError::InvalidReportFormat(value.to_string()))?,
+        })
+    }
+}
+
+impl FromStr for ReportFormats {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ReportFormats::try_from(s)
+    }
+}
+
+impl fmt::Display for ReportFormats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ReportFormats::Raw => "raw",
+            ReportFormats::Builtins => "builtins",
+            ReportFormats::BeforeRules => "before-rules",
+            ReportFormats::UserRules => "user-rules",
+            ReportFormats::AfterRules => "after-rules",
+            ReportFormats::LoggingRules => "logging-rules",
+            ReportFormats::Listening => "listening",
+            ReportFormats::Added => "added",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+// a rule of the `iptables -L -v -x` listings `ufw show raw` prints
+#[derive(Debug)]
+pub struct RawRule {
+    chain: String,
+    packets: u64,
+    bytes: u64,
+    target: Option<String>,
+    protocol: String,
+    input: String,
+    output: String,
+    source: String,
+    destination: String,
+    // module matches such as `tcp dpt:22`, kept verbatim
+    options: String,
+}
+
+impl RawRule {
+    pub fn chain(&self) -> &str {
+        &self.chain
+    }
+
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    pub fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub fn options(&self) -> &str {
+        &self.options
+    }
+}
+
+// negation (`!lo`, `!10.0.0.0/8`) isn't modeled, such rules are returned as ParseError::NegatedRule
+// instead of being read as their opposite
+pub fn parse_raw(output: &str) -> Vec<ParseResult<RawRule>> {
+    let is_opt = |token: &str| matches!(token, "--" | "-f" | "!f");
+    let mut chain = String::new();
+
+    output
+        .lines()
+        .filter_map(|line| {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.first() {
+                Some(&"Chain") => {
+                    chain = tokens.get(1).unwrap_or(&"").to_string();
+                    None
+                }
+                Some(token) if token.parse::<u64>().is_ok() => Some((chain.clone(), tokens, line)),
+                _ => None,
+            }
+        })
+        .map(|(chain, tokens, line)| {
+            let invalid = || ParseError::InvalidStatusRow(line.to_string());
+            let counter = |token: &str| token.parse::<u64>().map_err(|_| invalid());
+
+            // iptables leaves the target empty for rules without `-j`, ip6tables has no `opt` column at all
+            let (target, rest) = match (tokens.get(3), tokens.get(4)) {
+                (Some(opt), _) if is_opt(opt) => (None, &tokens[2..]),
+                (_, Some(opt)) if is_opt(opt) => (Some(tokens[2].to_string()), &tokens[3..]),
+                _ => (tokens.get(2).map(|t| t.to_string()), tokens.get(3..).unwrap_or(&[])),
+            };
+            let mut rest = rest.to_vec();
+            let negated_opt = match rest.get(1) {
+                Some(opt) if is_opt(opt) => rest.remove(1).starts_with('!'),
+                _ => false,
+            };
+            if rest.len() < 5 {
+                return Err(invalid());
+            }
+
+            if negated_opt || rest[..5].iter().any(|column| column.starts_with('!')) {
+                Err(ParseError::NegatedRule(line.trim().to_string()))?
+            }
+
+            Ok(RawRule {
+                chain,
+                packets: counter(tokens[0])?,
+                bytes: counter(tokens[1])?,
+                target,
+                protocol: rest[0].to_string(),
+                input: rest[1].to_string(),
+                output: rest[2].to_string(),
+                source: rest[3].to_string(),
+                destination: rest[4].to_string(),
+                options: rest[5..].join(" "),
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoggingLevel {
+    Off,
+    Low,
+    Medium,
+    High,
+    Full,
+}
+
+impl TryFrom<(&str, &str)> for LoggingLevel {
+    type Error = ParseError;
+
+    fn try_from(value: (&str, &str)) -> Result<Self, Self::Error> {
+        let (onoff, level) = value;
+
+        match onoff {
+            "on" => {
+                match level {
+                    "low" => Ok(LoggingLevel::Low),
+                    "medium" => Ok(LoggingLevel::Medium),
+                    "high" => Ok(LoggingLevel::High),
+                    "full" => Ok(LoggingLevel::Full),
+                    &_ => Err(ParseError::InvalidLoggingLevel(level.to_string())),
+                }
+            }
+            "off" => {
+                Ok(LoggingLevel::Off)
+            }
+            &_ => Err(ParseError::InvalidLoggingLevel(onoff.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for LoggingLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            LoggingLevel::Off => "off",
+            LoggingLevel::Low => "low",
+            LoggingLevel::Medium => "medium",
+            LoggingLevel::High => "high",
+            LoggingLevel::Full => "full",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+// `ufw version` prints e.g. `ufw 0.36.1`
+pub fn parse_version(text: &str) -> ParseResult<String> {
+    match Regex::new(r"ufw (\d+\.\d+(?:\.\d+)?)")
+        .unwrap()
+        .captures(text) {
+        None => {
+            let error_message = format!("Couldn't find a valid ufw version in {}", text);
+            Err(IOError(error_message))
+        }
+        Some(captures) => {
+            match captures.get(1) {
+                None => {
+                    let error_message = format!("Couldn't find a valid ufw version in {}", text);
+                    Err(IOError(error_message))
+                }
+                Some(capture) => {
+                    Ok(capture.as_str().to_string())
+                }
+            }
+        }
+    }
+}
+
+// whether ufw is enabled and its logging level, from `ufw status verbose`
+pub fn parse_info(text: &str) -> ParseResult<(bool, LoggingLevel)> {
+    // an inactive firewall only prints `Status: inactive`, the logging level is checked afterwards
+    let enabled = match Regex::new(r"Status:\s*((?:in)?active)")
+        .unwrap()
+        .captures(text) {
+        None => {
+            let error_message = format!("Couldn't find a valid status in: {}", text);
+            Err(IOError(error_message))
+        }
+        Some(captures) => {
+            match captures.get(1) {
+                None => {
+                    let error_message = format!("Couldn't find a valid status in: {}", text);
+                    Err(IOError(error_message))
+                }
+                Some(capture) => {
+                    Ok(capture.as_str().to_string())
+                }
+            }
+        }
+    }? == "active";
+    let logging_level = match Regex::new(r"Logging:\s*(on|off)\s*(?:\((\w+)\))?")
+        .unwrap()
+        .captures(text) {
+        // a freshly installed ufw which has never been enabled only prints `Status: inactive`
+        None if !enabled => Ok(LoggingLevel::Off),
+        None => {
+            Err(InvalidLoggingLevel(text.to_string()))
+        }
+        Some(captures) => {
+            let (state, level) = (captures.get(1), captures.get(2));
+            match (state, level) {
+                (Some(state), Some(level)) => LoggingLevel::try_from((state.as_str(), level.as_str())),
+                // logging is a state, not a level, ufw prints only `Logging: off` when it's disabled
+                (Some(state), None) if state.as_str() == "off" => Ok(LoggingLevel::Off),
+                _ => {
+                    let message = format!("Invalid logging level ({}) found.\
+                        Valid logging levels are: `low`, `medium`, `high`, `full`.\
+                        \nBeware that ufw has no checks on what you're setting the value to and allows anything.",
+                                          text.to_string());
+                    Err(InvalidLoggingLevel(message))
+                }
+            }
+        }
+    }?;
+
+    Ok((enabled, logging_level))
+}
+
+// the `Default:` line of `ufw status verbose`, inactive firewalls don't print one
+pub fn parse_defaults(text: &str) -> ParseResult<DefaultPolicies> {
+    if !text.lines().any(|line| line.starts_with("Status:")) {
+        Err(ParseError::InvalidDefaults(text.to_string()))?
+    }
+
+    Ok(parse_default_policies(text, false))
+}
+
+fn parse_default_policies(text: &str, legacy: bool) -> DefaultPolicies {
+    let defaults_regex = Regex::new(r"^Default:\s*.+").unwrap();
+    let single_default_regex = Regex::new(r"(\w+)\s*\((\w+)\)").unwrap();
+    let policies = text
+        .split("\n")
+        .filter(|text| defaults_regex.is_match(text))
+        .map(|text|
+            text
+                .split(": ")
+                .last()
+                // this is impossible since we match against the defaults_regex beforehand which assures that something is behind the colon
+                .unwrap_or("")
+                // entries are looked up by their direction (see `DefaultPolicies::get`), neither their count nor order matters
+                .split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(|x| match single_default_regex.captures(x) {
+                    Some(default) => {
+                        let rule_type = &default[1];
+                        let rule_direction = &default[2];
+
+                        DefaultPolicy {
+                            label: rule_direction.to_ascii_lowercase(),
+                            direction: RuleDirectionDefaults::try_from(rule_direction),
+                            policy: parse_default_policy(rule_type),
+                        }
+                    }
+                    // e.g. `Default: deny`, see `parse_with_version`
+                    None if legacy && !x.trim().contains(char::is_whitespace) => {
+                        DefaultPolicy {
+                            label: RuleDirectionDefaults::INCOMING.to_string(),
+                            direction: Ok(RuleDirectionDefaults::INCOMING),
+                            policy: parse_default_policy(x.trim()),
+                        }
+                    }
+                    None => {
+                        DefaultPolicy {
+                            label: x.trim().to_string(),
+                            direction: Err(ParseError::WrongRuleDirection(x.to_string())),
+                            policy: Err(ParseError::InvalidDefaults(x.to_string())),
+                        }
+                    }
+                })
+                .collect::<Vec<DefaultPolicy>>()
+        ).flatten()
+        .collect();
+
+    DefaultPolicies {
+        policies,
+    }
+}
+
+// the `Default:` line of `ufw status verbose` for a known ufw version,
+// releases before 0.35 only printed the incoming policy without a label (`Default: deny`)
+pub fn parse_with_version(text: &str, version: &str) -> ParseResult<DefaultPolicies> {
+    let version_numbers = version
+        .trim()
+        .split('.')
+        .map(|number| number.parse::<u32>())
+        .collect::<Result<Vec<u32>, ParseIntError>>()
+        .map_err(|_| ParseError::InvalidVersion(version.to_string()))?;
+
+    Ok(parse_default_policies(text, version_numbers < vec![0, 35]))
+}
+
+#[derive(Debug)]
+pub struct Ufw {
+    enabled: bool,
+    logging: LoggingLevel,
+    defaults: DefaultPolicies,
+    entries: Vec<(RuleEntry, bool)>,
+    // the numbers of the rows no RuleEntry can represent (e.g. `80,443/tcp`), along with why
+    unrepresented: Vec<(u16, ParseError)>,
+    // changes are applied through the command the state was read with
+    command: UfwCommand,
+}
+
+// keep old rules until submitting, the flag marks rules which are already known to ufw
+impl Ufw {
+    // the current state of the firewall. rows which can't be represented as a RuleEntry are left out of the
+    // entries and kept in `unrepresented` instead, their numbers keep the positions of new rules right
+    pub fn from_system(command: &UfwCommand) -> CommandResult<Ufw> {
+        let (enabled, logging) = command.info()?;
+        let defaults = command.defaults()?;
+        let mut entries = vec![];
+        let mut unrepresented = vec![];
+
+        // ufw numbers its rows consecutively, starting at 1
+        for (number, line) in (1..).zip(command.status_numbered()?) {
+            let entry = line
+                .map_err(|e| ParseError::InvalidStatusRow(format!("rule {}: {:#}", number, e)))
+                .and_then(|line| RuleEntry::try_from(&line));
+            match entry {
+                Ok(entry) => entries.push((entry, true)),
+                Err(err) => unrepresented.push((number, err)),
+            }
+        }
+
+        Ok(Ufw {
+            enabled,
+            logging,
+            defaults,
+            entries,
+            unrepresented,
+            command: command.clone(),
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn logging(&self) -> LoggingLevel {
+        self.logging
+    }
+
+    pub fn defaults(&self) -> &DefaultPolicies {
+        &self.defaults
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item=&RuleEntry> {
+        self.entries.iter().map(|(entry, _)| entry)
+    }
+
+    // the rows of ufw which aren't part of `entries`, by their number in `ufw status numbered`
+    pub fn unrepresented(&self) -> &[(u16, ParseError)] {
+        &self.unrepresented
+    }
+
+    pub fn add_rule(&mut self, entry: RuleEntry) {
+        self.entries.push((entry, false))
+    }
+
+    pub fn delete_rule(&mut self, entry_index: u16) -> Option<(RuleEntry, bool)> {
+        if usize::from(entry_index) >= self.entries.len() {
+            None
+        } else {
+            Some(self.entries.remove(entry_index.into()))
+        }
+    }
+
+    // only rules ufw accepted are marked as committed, the outputs of the others tell why they failed
+    pub fn submit(&mut self) -> CommandResult<Vec<Output>> {
+        let command = self.command.clone();
+        let mut outputs = vec![];
+
+        for index in 0..self.entries.len() {
+            if self.entries[index].1 {
+                continue;
+            }
+
+            let position = self.insert_position(index);
+            let arguments = self.submit_arguments(index);
+            let output = command.exec(arguments.iter().map(String::as_str).collect())?;
+            // a dry run only prints what ufw would do, the rule is still pending afterwards
+            if output.status.success() && !command.dry_run {
+                self.committed(index, position);
+            }
+            outputs.push(output);
+        }
+
+        Ok(outputs)
+    }
+
+    // ufw numbers its rules starting at 1 and can only insert before an existing rule, anything after the
+    // last rule is appended instead (`None`). the unrepresented rows are still in between the entries
+    fn insert_position(&self, index: usize) -> Option<u16> {
+        let committed = self.entries.iter().filter(|(_, committed)| *committed).count();
+        if index >= committed {
+            return None;
+        }
+
+        let mut position = index as u16 + 1;
+        for (number, _) in &self.unrepresented {
+            if *number <= position {
+                position += 1;
+            }
+        }
+
+        Some(position)
+    }
+
+    fn submit_arguments(&self, index: usize) -> Vec<String> {
+        let mut arguments = vec![];
+        if let Some(position) = self.insert_position(index) {
+            arguments.extend(vec!["insert".to_string(), position.to_string()]);
+        }
+        arguments.extend(self.entries[index].0.arguments());
+
+        arguments
+    }
+
+    // an inserted rule moves all following ones down by one
+    fn committed(&mut self, index: usize, position: Option<u16>) {
+        self.entries[index].1 = true;
+        if let Some(position) = position {
+            for (number, _) in self.unrepresented.iter_mut().filter(|(number, _)| *number >= position) {
+                *number += 1;
+            }
+        }
+    }
+
+    pub fn logging_command(&self) -> String {
+        format!("ufw logging {}", self.logging)
+    }
+
+    // a shell script recreating this firewall from scratch on another host.
+    // policies which couldn't be parsed or are disabled are left out, the target keeps its own default for them.
+    // ufw lists a rule without addresses once per ip version but creates both from a single command
+    pub fn migration_script(&self) -> String {
+        let mut lines = vec!["ufw --force reset".to_string()];
+        lines.extend(self.defaults
+            .iter()
+            .filter_map(|default| match (default.direction(), default.policy()) {
+                (Ok(direction), Ok(Some(policy))) => Some(format!("ufw default {} {}", policy, direction)),
+                _ => None,
+            }));
+        lines.push(self.logging_command());
+        let entries: Vec<&RuleEntry> = self.entries().collect();
+        let is_v6 = |entry: &&RuleEntry| entry.ip_version == Some(IpVersion::V6);
+        let is_companion = |v4: &&RuleEntry, v6: &&RuleEntry| v4.ip_version == Some(IpVersion::V4) && v4 == v6;
+        lines.extend(numbered::pair_companions(&entries, is_v6, is_companion)
+            .into_iter()
+            .filter_map(|(v4, v6)| v4.or(v6))
+            .map(|entry| format!("ufw {}", entry.to_string())));
+        lines.push("ufw --force enable".to_string());
+
+        lines.join("\n")
+    }
+
+    // ufw evaluates rules top-down, a rule that's covered by an earlier one will never match anything
+    pub fn shadowed_rules(&self) -> Vec<(&RuleEntry, &RuleEntry)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (entry, _))| {
+                self.entries[..index]
+                    .iter()
+                    .map(|(earlier, _)| earlier)
+                    .find(|earlier| earlier.covers(entry))
+                    .map(|earlier| (earlier, entry))
+            })
+            .collect()
+    }
+
+    pub fn is_rule_present(&self, rule: &RuleEntry) -> bool {
+        self.entries.iter().any(|(entry, _)| entry == rule)
+    }
+
+    pub fn rule_counts(&self) -> HashMap<RuleType, usize> {
+        let mut counts = HashMap::new();
+        for (entry, _) in &self.entries {
+            *counts.entry(entry.action.typ).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    // keeps the default policies and logging, unlike `ufw reset`
+    pub fn clear_user_rules(&self) -> CommandResult<()> {
+        let command = &self.command;
+        let mut numbers = command.rule_numbers()?;
+
+        // deleting a rule renumbers all following ones, deleting from the end keeps the remaining numbers valid
+        numbers.sort_unstable_by(|a, b| b.cmp(a));
+        for number in numbers {
+            command.delete(number)?;
+        }
+
+        Ok(())
+    }
+
+    // settings the config leaves out are kept as they are
+    pub fn apply_baseline(&self, config: &Config) -> CommandResult<()> {
+        let command = &self.command;
+
+        if let Some(policy) = config.default_incoming {
+            command.set_default(RuleDirectionDefaults::INCOMING, policy)?;
+        }
+        if let Some(policy) = config.default_outgoing {
+            command.set_default(RuleDirectionDefaults::OUTGOING, policy)?;
+        }
+        if let Some(level) = config.default_logging {
+            command.set_logging(level)?;
+        }
+
+        Ok(())
+    }
+
+    // only adds the missing rules and deletes the superfluous ones instead of resetting everything.
+    // new rules are added before anything is deleted so traffic allowed by both rulesets isn't interrupted,
+    // deletion goes by rule instead of number since adding rules renumbers the existing ones.
+    // the order of the rules is ignored, the missing ones are appended in the order of `desired`
+    pub fn apply_desired(&self, desired: &[RuleEntry]) -> CommandResult<ApplyReport> {
+        let command = &self.command;
+        let current = command.rules()?.into_iter().collect::<ParseResult<Vec<RuleEntry>>>()?;
+
+        let added: Vec<RuleEntry> = desired
+            .iter()
+            .filter(|rule| !current.contains(rule))
+            .cloned()
+            .collect();
+        let mut deleted: Vec<RuleEntry> = vec![];
+        for existing in current {
+            // `ufw delete` removes the v4 and v6 variant of a rule at once
+            if !desired.contains(&existing) && !deleted.contains(&existing) {
+                deleted.push(existing);
+            }
+        }
+
+        for rule in &added {
+            command.add_rule(rule)?;
+        }
+        for rule in &deleted {
+            command.delete_rule(rule)?;
+        }
+
+        Ok(ApplyReport {
+            added,
+            deleted,
+        })
+    }
+
+    // ufw can't apply several rules in one transaction, so stop at the first failure and report
+    // (via CommandError::PartialSubmit) which rules have been applied until then so they can be rolled back
+    pub fn submit_atomic(&mut self) -> CommandResult<Vec<usize>> {
+        let command = self.command.clone();
+        let mut applied = vec![];
+
+        for index in 0..self.entries.len() {
+            if self.entries[index].1 {
+                continue;
+            }
+
+            let position = self.insert_position(index);
+            let arguments = self.submit_arguments(index);
+            let result = match command.exec(arguments.iter().map(String::as_str).collect()) {
+                Ok(output) if output.status.success() => Ok(()),
+                Ok(output) => Err(UfwCommand::failure(&output)),
+                Err(err) => Err(CommandError::from(err)),
+            };
+            if let Err(err) = result {
+                return Err(CommandError::PartialSubmit { applied, failed: index, source: Box::new(err) });
+            }
+
+            if !command.dry_run {
+                self.committed(index, position);
+            }
+            applied.push(index);
+        }
+
+        Ok(applied)
+    }
+}
+
+#[derive(Debug)]
+pub struct ApplyReport {
+    added: Vec<RuleEntry>,
+    deleted: Vec<RuleEntry>,
+}
+
+impl ApplyReport {
+    pub fn added(&self) -> &[RuleEntry] {
+        &self.added
+    }
+
+    pub fn deleted(&self) -> &[RuleEntry] {
+        &self.deleted
+    }
+}
+
+// the rules table starts after the `--  ------  ----` separator, with or without the `[ 1]` column of `ufw status numbered`
+fn parse_rule_table(text: &str) -> Vec<ParseResult<RuleEntry>> {
+    text
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("--"))
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        // the rows of the plain table are numbered ones without their number
+        .map(|line| if line.trim_start().starts_with('[') {
+            RuleEntry::from_numbered_row(line)
+        } else {
+            RuleEntry::from_numbered_row(&format!("[ 0] {}", line.trim_start()))
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct UfwPort {
+    pub(crate) number: u16,
+    pub(crate) end_number: Option<u16>,
+    pub(crate) protocols: Vec<ParseResult<Protocol>>,
+}
+
+fn parse_default_policy(policy: &str) -> ParseResult<Option<RuleType>> {
+    match policy {
+        "disabled" => Ok(None),
+        policy => RuleType::try_from(policy).map(Some),
+    }
+}
+
+impl UfwPort {
+    pub fn number(&self) -> u16 {
+        self.number
+    }
+
+    pub fn end_number(&self) -> Option<u16> {
+        self.end_number
+    }
+
+    pub fn protocols(&self) -> &Vec<ParseResult<Protocol>> {
+        &self.protocols
+    }
+}
+
+// the syntax of a profile's `ports` value, e.g. `80/tcp` or `6000:6007/udp`
+impl fmt::Display for UfwPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let number = match self.end_number {
+            Some(end_number) => format!("{}:{}", self.number, end_number),
+            None => self.number.to_string(),
+        };
+        let protocols: Vec<&Protocol> = self.protocols.iter().flatten().collect();
+
+        match protocols.as_slice() {
+            [protocol] => write!(f, "{}/{}", number, protocol),
+            // no protocol means both tcp and udp
+            _ => write!(f, "{}", number),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UfwCommand {
+    pub(crate) executable: PathBuf,
+    // where ufw keeps the logging level, it's read back from there since `ufw status` only shows it while active
+    pub(crate) conf_file: PathBuf,
+    // e.g. `sudo`, the executable is passed to it as the first argument
+    pub(crate) prefix: Vec<String>,
+    // `--dry-run` makes ufw print the rules it would write instead of applying them
+    pub(crate) dry_run: bool,
+}
+
+pub type UfwCommandOutput = Output;
+
+// ufw rewrites its rule files on every change and isn't safe to run concurrently,
+// all sync invocations within this process are serialized through this lock (AsyncUfwCommand has its own)
+static EXEC_LOCK: Mutex<()> = Mutex::new(());
+
+struct LineNumberRuleEntry {}
+
+struct LineNumberField {
+    name: String,
+    value: Vec<ParseResult<UfwPort>>, // TOOD correct type?
+}
+
+impl UfwCommand {
+    pub fn new() -> UfwCommand {
+        UfwCommand {
+            executable: PathBuf::from("/usr/bin/ufw"),
+            conf_file: PathBuf::from("/etc/ufw/ufw.conf"),
+            prefix: vec![],
+            dry_run: false,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> UfwCommand {
+        let mut command = UfwCommand::new();
+        if let Some(executable) = &config.ufw_executable {
+            command = command.with_executable(executable);
+        }
+        if let Some(sudo) = &config.sudo {
+            command.prefix = sudo.prefix();
+        }
+
+        command
+    }
+
+    pub fn with_executable<P: Into<PathBuf>>(self, executable_path: P) -> UfwCommand {
+        UfwCommand {
+            executable: executable_path.into(),
+            ..self
+        }
+    }
+
+    pub fn with_conf_file<P: Into<PathBuf>>(self, conf_file: P) -> UfwCommand {
+        UfwCommand {
+            conf_file: conf_file.into(),
+            ..self
+        }
+    }
+
+    pub fn dry_run(self, on: bool) -> UfwCommand {
+        UfwCommand {
+            dry_run: on,
+            ..self
+        }
+    }
+
+    // arguments every invocation starts with, before the subcommand
+    pub(crate) fn global_arguments(&self) -> Vec<&str> {
+        if self.dry_run {
+            vec!["--dry-run"]
+        } else {
+            vec![]
+        }
+    }
+
+    fn parse_action(arguments: Vec<&str>) -> ParseResult<UfwAction> {
+        let rule_type = arguments.get(0)
+            .ok_or(ParseError::WrongRuleType(String::from("couldn't get rule type")))?.to_owned();
+        let rule_direction = arguments.get(1)
+            .ok_or(ParseError::WrongRuleType(String::from("couldn't get rule direction")))?.to_owned();
+
+        Ok(UfwAction {
+            typ: RuleType::try_from(rule_type)?,
+            direction: RuleDirection::try_from(rule_direction)?,
+        })
+    }
+
+    pub(crate) fn parse_stdout(o: Vec<u8>) -> ParseResult<String> {
+        match str::from_utf8(&o) {
+            Ok(val) => {
+                Ok(val.to_string())
+            }
+            Err(err) => {
+                Err(IOError(err.to_string()))
+            }
+        }
+    }
+
+    // ufw's stderr isn't guaranteed to be valid utf8, a lossy decode still keeps the message readable
+    fn parse_stderr(output: &Output) -> String {
+        String::from_utf8_lossy(&output.stderr).trim().to_string()
+    }
+
+    pub(crate) fn failure(output: &Output) -> CommandError {
+        let stderr = UfwCommand::parse_stderr(output);
+
+        // `ERROR: You need to be root to run this script`
+        if stderr.contains("You need to be root") {
+            CommandError::RootRequired
+        } else {
+            CommandError::Failed { code: output.status.code(), stderr }
+        }
+    }
+
+    pub fn version(&self) -> CommandResult<String> {
+        let output = self.exec(vec!["version"])?;
+
+        if output.status.success() {
+            let text = UfwCommand::parse_stdout(output.stdout)?;
+
+            Ok(parse_version(&text)?)
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn info(&self) -> CommandResult<(bool, LoggingLevel)> {
+        let output = self.exec(vec!["status", "verbose"])?;
+
+        if output.status.success() {
+            let text = UfwCommand::parse_stdout(output.stdout)?;
+
+            Ok(parse_info(&text)?)
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn defaults(&self) -> CommandResult<DefaultPolicies> {
+        let output = self.exec(vec!["status", "verbose"])?;
+        if output.status.success() {
+            let text = UfwCommand::parse_stdout(output.stdout)?;
+
+            Ok(parse_defaults(&text)?)
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn set_logging(&self, level: LoggingLevel) -> CommandResult<()> {
+        let level = level.to_string();
+        let output = self.exec(vec!["logging", &level])?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn set_default(&self, direction: RuleDirectionDefaults, policy: RuleType) -> CommandResult<()> {
+        // a default policy can only allow, deny or reject
+        if policy == RuleType::LIMIT {
+            Err(ParseError::WrongRuleType(format!("`{}` can't be used as the default policy", policy)))?
+        }
+        let (policy, direction) = (policy.to_string(), direction.to_string());
+        let output = self.exec(vec!["default", &policy, &direction])?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn enable(&self) -> CommandResult<()> {
+        // without `--force` ufw asks whether existing ssh connections may be disrupted
+        let output = self.exec(vec!["--force", "enable"])?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn disable(&self) -> CommandResult<()> {
+        let output = self.exec(vec!["disable"])?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    /// **Destructive**: disables ufw and removes every rule, ufw backs up the old rules files to `/etc/ufw/*.rules.<timestamp>`
+    pub fn reset(&self) -> CommandResult<()> {
+        let output = self.exec(vec!["--force", "reset"])?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn reload(&self) -> CommandResult<()> {
+        let output = self.exec(vec!["reload"])?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    // ufw doesn't complain about every level it fails to apply, reading the level back from ufw.conf catches that
+    pub fn set_logging_verified(&self, level: LoggingLevel) -> CommandResult<()> {
+        self.set_logging(level)?;
+
+        let current = UfwConf::from_file(&self.conf_file)?.loglevel();
+        if current == level {
+            Ok(())
+        } else {
+            Err(InvalidLoggingLevel(format!("requested logging level `{}` but ufw reports `{}`", level, current)).into())
+        }
+    }
+
+    pub fn status(&self) -> CommandResult<Vec<ParseResult<RuleEntry>>> {
+        let output = self.exec(vec!["status"])?;
+
+        if output.status.success() {
+            let text = UfwCommand::parse_stdout(output.stdout)?;
+
+            Ok(parse_rule_table(&text))
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    // the verbatim `ufw status verbose` text, for whatever the parsers above don't cover
+    pub fn raw_status(&self) -> CommandResult<String> {
+        let output = self.exec(vec!["status", "verbose"])?;
+
+        if output.status.success() {
+            Ok(UfwCommand::parse_stdout(output.stdout)?)
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn status_full(&self) -> CommandResult<Vec<ParseResult<RuleEntry>>> {
+        let output = self.exec(vec!["status", "verbose"])?;
+
+        if output.status.success() {
+            let text = UfwCommand::parse_stdout(output.stdout)?;
+
+            Ok(parse_rule_table(&text))
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn rule_numbers(&self) -> CommandResult<Vec<u16>> {
+        let output = self.exec(vec!["status", "numbered"])?;
+
+        if output.status.success() {
+            let text = UfwCommand::parse_stdout(output.stdout)?;
+
+            let re = Regex::new(r"^\s*\[\s*(\d+)\]").unwrap();
+            text
+                .lines()
+                .filter_map(|line| re.captures(line))
+                .map(|captures| captures[1].parse::<u16>().map_err(|e| ParseError::InvalidStatusRow(e.to_string()).into()))
+                .collect()
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn rules(&self) -> CommandResult<Vec<ParseResult<RuleEntry>>> {
+        let output = self.exec(vec!["status", "numbered"])?;
+
+        if output.status.success() {
+            let text = UfwCommand::parse_stdout(output.stdout)?;
+
+            Ok(text
+                .lines()
+                .filter(|line| line.trim_start().starts_with('['))
+                .map(RuleEntry::from_numbered_row)
+                .collect())
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    // one result per row, a row that fails to parse doesn't hide the others
+    pub fn status_numbered(&self) -> CommandResult<Vec<Result<Line>>> {
+        let output = self.exec(vec!["status", "numbered"])?;
+
+        if output.status.success() {
+            let text = UfwCommand::parse_stdout(output.stdout)?;
+
+            Ok(numbered::parse_status(&text))
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn add_rule(&self, entry: &RuleEntry) -> CommandResult<()> {
+        let arguments = entry.arguments();
+        let output = self.exec(arguments.iter().map(String::as_str).collect())?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn delete_rule(&self, entry: &RuleEntry) -> CommandResult<()> {
+        let arguments = entry.arguments();
+        let mut args = vec!["--force", "delete"];
+        args.extend(arguments.iter().map(String::as_str));
+        let output = self.exec(args)?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn delete(&self, number: u16) -> CommandResult<()> {
+        let number = number.to_string();
+        // without `--force` ufw asks for confirmation on stdin
+        let output = self.exec(vec!["--force", "delete", &number])?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(UfwCommand::failure(&output))
         }
     }
 
-    pub fn defaults(&self) -> ParseResult<Vec<(ParseResult<RuleDirectionDefaults>, ParseResult<RuleType>)>> {
-        let output = self.exec(vec!["status", "verbose"]).map_err(|e| IOError(e.to_string()))?;
+    pub fn app_list(&self) -> CommandResult<Vec<String>> {
+        let output = self.exec(vec!["app", "list"])?;
+
         if output.status.success() {
-            let text = match str::from_utf8(&*output.stdout) {
-                Ok(val) => {
-                    Ok(val)
-                }
-                Err(err) => {
-                    Err(IOError(err.to_string()))
-                }
-            }?;
+            let text = UfwCommand::parse_stdout(output.stdout)?;
 
-            let defaults_regex = Regex::new(r"^Default:\s*.+").unwrap();
-            let single_default_regex = Regex::new(r"(\w+)\s+\((\w+)\)").unwrap();
-            Ok(text
-                .split("\n")
-                .filter(|text| defaults_regex.is_match(text))
-                .map(|text|
-                    text
-                        .split(": ")
-                        .last()
-                        // this is impossible since we match against the defaults_regex beforehand which assures that something is behind the colon
-                        .unwrap_or("")
-                        .split(", ")
-                        .map(|x| {
-                            let default = single_default_regex.captures(x).unwrap();
-
-                            let rule_type = default.get(1);
-                            let rule_direction = default.get(2);
-
-                            if rule_direction.is_some() & &rule_type.is_some() {
-                                let rule_direction: String = rule_direction.unwrap().as_str().into();
-                                let rule_type: String = rule_type.unwrap().as_str().into();
-
-                                let direction = RuleDirectionDefaults::try_from(&*rule_direction);
-                                let rtype = RuleType::try_from(&*rule_type);
-
-                                (direction, rtype)
-                            } else {
-                                (Err(ParseError::WrongRuleDirection(text.to_string())), Err(ParseError::WrongRuleDirection(text.to_string())))
-                            }
-                        })
-                        .collect::<Vec<(ParseResult<RuleDirectionDefaults>, ParseResult<RuleType>)>>()
-                ).flatten()
-                .collect())
+            Ok(parse_app_list(&text))
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn app_info(&self, name: &str) -> CommandResult<ApplicationEntry> {
+        let output = self.exec(vec!["app", "info", name])?;
+
+        if output.status.success() {
+            let text = UfwCommand::parse_stdout(output.stdout)?;
+
+            Ok(parse_app_info(&text)?)
+        } else {
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub fn show(&self, report: &ReportFormats) -> CommandResult<String> {
+        let report = report.to_string();
+        let output = self.exec(vec!["show", &report])?;
+
+        if output.status.success() {
+            Ok(UfwCommand::parse_stdout(output.stdout)?)
         } else {
-            Err(ParseError::InvalidDefaults(format!("{:?}\n\n{:?}", str::from_utf8(&*output.stdout), str::from_utf8(&*output.stderr))))
+            Err(UfwCommand::failure(&output))
+        }
+    }
+
+    pub(crate) fn exec(&self, args: Vec<&str>) -> io::Result<UfwCommandOutput> {
+        // a panic while holding the lock doesn't leave anything inconsistent behind, so poisoning can be ignored
+        let _guard = EXEC_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        self.command(args).output()
+    }
+
+    // the invocation of ufw with `args`, prefixed by e.g. `sudo`
+    pub(crate) fn command(&self, args: Vec<&str>) -> Command {
+        let mut command = match self.prefix.split_first() {
+            Some((program, prefix_args)) => {
+                let mut command = Command::new(program);
+                command.args(prefix_args).arg(&self.executable);
+                command
+            }
+            None => Command::new(&self.executable),
+        };
+        command.args(self.global_arguments()).args(args);
+
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    // a shell script standing in for ufw, named after the test so parallel tests don't share one
+    fn stub_ufw(name: &str, script: &str) -> UfwCommand {
+        let path = std::env::temp_dir().join(format!("ufw-parser-{}-{}", std::process::id(), name));
+        fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        UfwCommand::new().with_executable(path)
+    }
+
+    fn pending(command: UfwCommand, entries: Vec<RuleEntry>) -> Ufw {
+        Ufw {
+            enabled: true,
+            logging: LoggingLevel::Low,
+            defaults: DefaultPolicies { policies: vec![] },
+            entries: entries.into_iter().map(|entry| (entry, false)).collect(),
+            unrepresented: vec![],
+            command,
+        }
+    }
+
+    #[test]
+    fn submit_marks_only_accepted_rules_as_committed() {
+        let command = stub_ufw("submit", r#"case "$*" in *23*) echo "ERROR: rejected" >&2; exit 1;; esac"#);
+        let mut ufw = pending(command, vec![
+            RuleEntryBuilder::new().allow().to_port(22).build().unwrap(),
+            RuleEntryBuilder::new().allow().to_port(23).build().unwrap(),
+        ]);
+
+        let outputs = ufw.submit().unwrap();
+        assert!(outputs[0].status.success());
+        assert!(!outputs[1].status.success());
+        assert_eq!(ufw.entries.iter().map(|(_, committed)| *committed).collect::<Vec<bool>>(), vec![true, false]);
+    }
+
+    #[test]
+    fn report_formats_round_trip() {
+        let tokens = ["raw", "builtins", "before-rules", "user-rules", "after-rules", "logging-rules", "listening", "added"];
+
+        for token in tokens.iter() {
+            assert_eq!(token.parse::<ReportFormats>().unwrap().to_string(), *token);
+            assert_eq!(ReportFormats::try_from(token.to_uppercase().as_str()).unwrap().to_string(), *token);
+        }
+        assert!(matches!("summary".parse::<ReportFormats>(), Err(ParseError::InvalidReportFormat(token)) if token == "summary"));
+    }
+
+    #[test]
+    fn failures_carry_the_decoded_stderr() {
+        let command = stub_ufw("stderr", r#"printf 'ERROR: bad rule \377\n' >&2; exit 1"#);
+
+        let err = command.version().unwrap_err();
+        assert!(matches!(&err, CommandError::Failed { code: Some(1), stderr } if stderr == "ERROR: bad rule \u{fffd}"));
+        assert_eq!(err.to_string(), "ufw execution unsuccessful (exit code 1): ERROR: bad rule \u{fffd}");
+    }
+
+    #[test]
+    fn submit_atomic_reports_the_applied_prefix() {
+        let log = std::env::temp_dir().join(format!("ufw-parser-{}-atomic.log", std::process::id()));
+        let _ = fs::remove_file(&log);
+        let command = stub_ufw("atomic", &format!(r#"echo "$*" >> {}; case "$*" in *443*) exit 1;; esac"#, log.display()));
+        let rule = |port| RuleEntryBuilder::new().allow().to_port(port).build().unwrap();
+        let mut ufw = pending(command, vec![rule(22), rule(80), rule(443), rule(8080)]);
+        ufw.entries[0].1 = true;
+
+        assert!(matches!(ufw.submit_atomic(), Err(CommandError::PartialSubmit { applied, failed: 2, .. }) if applied == vec![1]));
+        // the already known rule isn't submitted again, nothing after the failed one is submitted at all
+        assert_eq!(fs::read_to_string(&log).unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn status_full_keeps_the_direction() {
+        let command = stub_ufw("status-full", r#"cat <<'X'
+Status: active
+Logging: on (low)
+Default: deny (incoming), allow (outgoing), disabled (routed)
+New profiles: skip
+
+To                         Action      From
+--                         ------      ----
+22/tcp                     ALLOW IN    Anywhere
+10.0.0.1 53/udp            ALLOW OUT   Anywhere on eth0
+Anywhere on eth1           ALLOW FWD   Anywhere on eth0
+X"#);
+
+        let rules: Vec<RuleEntry> = command.status_full().unwrap().into_iter().map(Result::unwrap).collect();
+        assert!(matches!(rules[0].action.direction, RuleDirection::IN));
+        assert!(matches!(rules[1].action.direction, RuleDirection::OUT));
+        assert!(matches!(rules[2].action.direction, RuleDirection::FWD));
+        assert_eq!(rules[1].interface.as_deref(), Some("eth0"));
+        assert_eq!((rules[2].interface.as_deref(), rules[2].out_interface.as_deref()), (Some("eth0"), Some("eth1")));
+    }
+
+    #[test]
+    fn rule_names_parse_in_any_case_and_render_lowercase() {
+        for token in ["ALLOW", "allow", "Allow"].iter() {
+            assert_eq!(RuleType::try_from(*token).unwrap().to_string(), "allow");
+        }
+        assert_eq!(RuleDirection::try_from("OUT").unwrap().to_string(), "out");
+        assert!(matches!(RuleDirection::try_from("fwd").unwrap(), RuleDirection::FWD));
+        assert_eq!(RuleDirectionDefaults::try_from("Incoming").unwrap().to_string(), "incoming");
+        assert!(RuleType::try_from("permit").is_err());
+    }
+
+    #[test]
+    fn parse_rule_spec_round_trips_rules() {
+        let specs = [
+            "allow in to any port 22",
+            "deny out on eth0 proto tcp from 10.0.0.1 to any port 443",
+            "route allow in on eth0 out on eth1 to any",
+            "allow in to any app OpenSSH",
+            "allow in to any port 80 comment 'web  server'",
+        ];
+
+        for spec in specs.iter() {
+            assert_eq!(parse_rule_spec(spec).unwrap().to_string(), *spec);
+        }
+    }
+
+    #[test]
+    fn parse_rule_spec_keeps_every_token() {
+        let route = parse_rule_spec("route allow in on eth0 out on eth1").unwrap();
+        assert_eq!(route.interface.as_deref(), Some("eth0"));
+        assert_eq!(route.out_interface.as_deref(), Some("eth1"));
+
+        let application = parse_rule_spec("allow OpenSSH").unwrap();
+        assert_eq!(application.destination_application, Some("OpenSSH".to_string()));
+        assert_eq!(application.destination_port, None);
+
+        let simple = parse_rule_spec("allow 22/udp").unwrap();
+        assert_eq!(simple.destination_port, Some(22));
+        assert!(matches!(simple.proto, Protocol::UDP));
+
+        let full = parse_rule_spec("deny out on eth0 proto tcp from 10.0.0.1 to any port 443").unwrap();
+        assert!(matches!(full.action.direction, RuleDirection::OUT));
+        assert_eq!(full.destination_port, Some(443));
+
+        let comment = parse_rule_spec("allow 22 comment 'it'\\''s  ssh'").unwrap();
+        assert_eq!(comment.comment, Some("it's  ssh".to_string()));
+    }
+
+    #[test]
+    fn parse_rule_spec_rejects_incomplete_or_unsupported_rules() {
+        for spec in ["allow", "route allow", "deny proto tcp", "allow in log 22", "allow proto tcp in on eth0", "allow from 10.0.0.1 to ::1", "allow proto tcp to any app OpenSSH",
+            "allow 22 comment", "allow 22 comment 'open"].iter() {
+            assert!(matches!(parse_rule_spec(spec), Err(ParseError::InvalidRuleSpec(_))), "{}", spec);
+        }
+        assert!(parse_rule_spec("allow 22x").is_err());
+    }
+
+    #[test]
+    fn parse_rule_spec_reads_port_ranges() {
+        let rule = parse_rule_spec("allow 6000:6007/tcp").unwrap();
+        assert_eq!((rule.destination_port, rule.destination_end_port), (Some(6000), Some(6007)));
+
+        let full = parse_rule_spec("allow from any port 1000:2000 to any port 22 proto udp").unwrap();
+        assert_eq!((full.source_port, full.source_end_port), (Some(1000), Some(2000)));
+        assert_eq!((full.destination_port, full.destination_end_port), (Some(22), None));
+
+        assert!(parse_rule_spec("allow 6000:6007").is_err());
+        assert!(matches!(parse_rule_spec("allow 6007:6000/tcp"), Err(ParseError::InvalidPortRange(range)) if range == "6007:6000"));
+    }
+
+    #[test]
+    fn concurrent_commands_run_one_at_a_time() {
+        let running = std::env::temp_dir().join(format!("ufw-parser-{}-running", std::process::id()));
+        let _ = fs::remove_dir(&running);
+        // fails if another invocation is still running
+        let command = stub_ufw("serialized", &format!("mkdir {0} || exit 3; sleep 0.05; rmdir {0}; echo 'ufw 0.36.1'", running.display()));
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let executable = command.executable.clone();
+                std::thread::spawn(move || UfwCommand { executable, ..UfwCommand::new() }.version())
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap().unwrap(), "0.36.1");
+        }
+    }
+
+    #[test]
+    fn shadowed_rules_finds_rules_behind_broader_ones() {
+        let spec = |spec: &str| parse_rule_spec(spec).unwrap();
+        let entries: Vec<(RuleEntry, bool)> = vec![
+            spec("allow from 10.0.0.0/8 to any port 22"),
+            spec("deny from 10.1.0.0/16 to any port 22"),
+            spec("allow to any port 80 proto tcp"),
+            spec("deny from 192.168.0.1 to any port 443 proto tcp"),
+        ].into_iter().map(|entry| (entry, true)).collect();
+        let ufw = Ufw { entries, ..pending(UfwCommand::new(), vec![]) };
+
+        let index = |rule: &RuleEntry| ufw.entries.iter().position(|(entry, _)| std::ptr::eq(entry, rule)).unwrap();
+        let shadowed: Vec<(usize, usize)> = ufw.shadowed_rules().into_iter().map(|(earlier, rule)| (index(earlier), index(rule))).collect();
+        assert_eq!(shadowed, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn renders_the_full_syntax_in_ufw_order() {
+        let rule = parse_rule_spec("allow to 192.168.1.1 port 22 from 10.0.0.0/8 proto tcp").unwrap();
+
+        assert_eq!(rule.to_string(), "allow in proto tcp from 10.0.0.0/8 to 192.168.1.1 port 22");
+    }
+
+    #[test]
+    fn set_logging_verified_fails_on_a_mismatching_readback() {
+        let conf = std::env::temp_dir().join(format!("ufw-parser-{}-logging-readback.conf", std::process::id()));
+        fs::write(&conf, "ENABLED=yes\nLOGLEVEL=low\n").unwrap();
+        let command = stub_ufw("logging-readback", "exit 0").with_conf_file(&conf);
+
+        assert!(command.set_logging_verified(LoggingLevel::Low).is_ok());
+        match command.set_logging_verified(LoggingLevel::High) {
+            Err(CommandError::Parse(ParseError::InvalidLoggingLevel(message))) => assert!(message.contains("`high`") && message.contains("`low`")),
+            other => panic!("expected a mismatching logging level, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn set_logging_verified_works_on_an_inactive_firewall() {
+        let conf = std::env::temp_dir().join(format!("ufw-parser-{}-logging-inactive.conf", std::process::id()));
+        fs::write(&conf, "ENABLED=no\nLOGLEVEL=low\n").unwrap();
+        // like ufw, the level is written to ufw.conf while the status doesn't show it
+        let script = format!("case \"$1\" in\n  logging) sed -i \"s/^LOGLEVEL=.*/LOGLEVEL=$2/\" {};;\n  *) echo 'Status: inactive';;\nesac", conf.display());
+        let command = stub_ufw("logging-inactive", &script).with_conf_file(&conf);
+        assert_eq!(command.info().unwrap(), (false, LoggingLevel::Off));
+
+        command.set_logging_verified(LoggingLevel::Medium).unwrap();
+        assert_eq!(UfwConf::from_file(&conf).unwrap().loglevel(), LoggingLevel::Medium);
+    }
+
+    #[test]
+    fn parse_defaults_keys_policies_by_direction() {
+        let defaults = parse_defaults("Status: active\nDefault: deny (incoming), allow (outgoing), disabled (routed)\n").unwrap();
+
+        assert!(matches!(defaults.incoming(), Some(Ok(Some(RuleType::DENY)))));
+        assert!(matches!(defaults.outgoing(), Some(Ok(Some(RuleType::ALLOW)))));
+        assert!(matches!(defaults.routed(), Some(Ok(None))));
+    }
+
+    #[test]
+    fn logging_command_renders_every_level() {
+        let levels = [
+            (LoggingLevel::Off, "ufw logging off"),
+            (LoggingLevel::Low, "ufw logging low"),
+            (LoggingLevel::Medium, "ufw logging medium"),
+            (LoggingLevel::High, "ufw logging high"),
+            (LoggingLevel::Full, "ufw logging full"),
+        ];
+
+        for (level, command) in levels.iter() {
+            let ufw = Ufw { logging: *level, ..pending(UfwCommand::new(), vec![]) };
+            assert_eq!(ufw.logging_command(), *command);
+        }
+    }
+
+    #[test]
+    fn detects_missing_root_privileges() {
+        let command = stub_ufw("root", "echo 'ERROR: You need to be root to run this script' >&2; exit 1");
+
+        let err = command.version().unwrap_err();
+        assert!(matches!(err, CommandError::RootRequired));
+        assert_eq!(err.to_string(), "ufw requires root; run with sudo");
+    }
+
+    #[test]
+    fn rule_counts_groups_by_action() {
+        let rules = ["allow 22", "allow 80", "deny 23", "limit 2222", "allow 443"];
+        let ufw = pending(UfwCommand::new(), rules.iter().map(|spec| parse_rule_spec(spec).unwrap()).collect());
+
+        let counts = ufw.rule_counts();
+        assert_eq!(counts.get(&RuleType::ALLOW), Some(&3));
+        assert_eq!(counts.get(&RuleType::DENY), Some(&1));
+        assert_eq!(counts.get(&RuleType::LIMIT), Some(&1));
+        assert_eq!(counts.get(&RuleType::REJECT), None);
+    }
+
+    #[test]
+    fn parses_expanded_and_named_application_rows() {
+        let expanded = RuleEntry::from_numbered_row("[ 1] 22/tcp                     ALLOW IN    Anywhere").unwrap();
+        let named = RuleEntry::from_numbered_row("[ 2] OpenSSH                    ALLOW IN    Anywhere").unwrap();
+        assert_eq!(named.destination_application, Some("OpenSSH".to_string()));
+
+        let application = Application {
+            filepath: PathBuf::from("openssh"),
+            entries: vec![parse_app_info("Profile: OpenSSH\nTitle: Secure shell server\nDescription: OpenSSH\n\nPort:\n  22/tcp\n")],
+        };
+        let resolved = named.resolve_applications(&[application]).unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0], expanded);
+        assert_eq!(named.resolve_applications(&[]), None);
+    }
+
+    #[test]
+    fn command_errors_have_a_kind_per_failure() {
+        let missing = UfwCommand::new().with_executable("/nonexistent/ufw");
+        assert!(matches!(missing.version(), Err(CommandError::Spawn(_))));
+
+        let failing = stub_ufw("error-kinds-failing", "exit 2");
+        assert!(matches!(failing.version(), Err(CommandError::Failed { code: Some(2), .. })));
+
+        let root = stub_ufw("error-kinds-root", "echo 'ERROR: You need to be root to run this script' >&2; exit 1");
+        assert!(matches!(root.version(), Err(CommandError::RootRequired)));
+
+        let garbage = stub_ufw("error-kinds-garbage", "echo 'not a version'");
+        assert!(matches!(garbage.version(), Err(CommandError::Parse(ParseError::IOError(_)))));
+    }
+
+    // answers `status numbered` with a fixed table and logs every other invocation
+    fn logging_stub(name: &str, table: &str) -> (UfwCommand, std::path::PathBuf) {
+        let log = std::env::temp_dir().join(format!("ufw-parser-{}-{}.log", std::process::id(), name));
+        let _ = fs::remove_file(&log);
+        let script = format!("case \"$1\" in\n  status) cat <<'X'\nStatus: active\n\n     To                         Action      From\n     --                         ------      ----\n{}\nX\n  ;;\n  *) echo \"$*\" >> {};;\nesac", table, log.display());
+
+        (stub_ufw(name, &script), log)
+    }
+
+    #[test]
+    fn apply_desired_only_runs_the_delta() {
+        let (command, log) = logging_stub("apply-desired", "[ 1] 22/tcp                     ALLOW IN    Anywhere\n\
+            [ 2] 80/tcp                     ALLOW IN    Anywhere\n\
+            [ 3] 22/tcp (v6)                ALLOW IN    Anywhere (v6)\n\
+            [ 4] 80/tcp (v6)                ALLOW IN    Anywhere (v6)");
+        let ufw = pending(command, vec![]);
+        let desired = [parse_rule_spec("allow 22/tcp").unwrap(), parse_rule_spec("allow 443/tcp").unwrap()];
+
+        let report = ufw.apply_desired(&desired).unwrap();
+        assert_eq!(report.added().len(), 1);
+        assert_eq!(report.deleted().len(), 1);
+        assert_eq!(fs::read_to_string(&log).unwrap(), "allow in proto tcp to any port 443\n--force delete allow in proto tcp to any port 80\n");
+    }
+
+    fn config(name: &str, content: &str) -> Config {
+        let path = std::env::temp_dir().join(format!("ufw-parser-{}-{}.toml", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+
+        Config::from_file(path).unwrap()
+    }
+
+    #[test]
+    fn apply_baseline_issues_the_configured_settings() {
+        let (command, log) = logging_stub("baseline", "");
+        let ufw = pending(command, vec![]);
+
+        ufw.apply_baseline(&config("baseline", "block_after_times = 3\ndefault_logging = \"medium\"\ndefault_incoming = \"deny\"\ndefault_outgoing = \"allow\"\n")).unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), "default deny incoming\ndefault allow outgoing\nlogging medium\n");
+
+        fs::remove_file(&log).unwrap();
+        ufw.apply_baseline(&config("baseline-partial", "block_after_times = 3\ndefault_incoming = \"reject\"\n")).unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), "default reject incoming\n");
+    }
+
+    #[test]
+    fn common_rules() {
+        assert_eq!(RuleEntry::allow_ssh().to_string(), "allow in proto tcp to any port 22");
+        assert_eq!(RuleEntry::allow_http().to_string(), "allow in proto tcp to any port 80");
+        assert_eq!(RuleEntry::allow_https().to_string(), "allow in proto tcp to any port 443");
+    }
+
+    #[test]
+    fn parse_raw_flags_negated_rules() {
+        let raw = "Chain ufw-user-input (1 references)\n    pkts      bytes target     prot opt in     out     source               destination\n\
+            \x20      5      300 ACCEPT     tcp  --  *      *       0.0.0.0/0            0.0.0.0/0            tcp dpt:22\n\
+            \x20      0        0 DROP       all  --  !lo    *       0.0.0.0/0            127.0.0.0/8\n\
+            \x20      0        0 DROP       all  --  *      *      !10.0.0.0/8           0.0.0.0/0\n";
+
+        let rules = parse_raw(raw);
+        let accepted = rules[0].as_ref().unwrap();
+        assert_eq!((accepted.chain(), accepted.target(), accepted.packets(), accepted.options()), ("ufw-user-input", Some("ACCEPT"), 5, "tcp dpt:22"));
+        assert!(matches!(&rules[1], Err(ParseError::NegatedRule(row)) if row.contains("!lo")));
+        assert!(matches!(&rules[2], Err(ParseError::NegatedRule(row)) if row.contains("!10.0.0.0/8")));
+    }
+
+    #[test]
+    fn is_rule_present_ignores_the_rule_number() {
+        let mut ufw = pending(UfwCommand::new(), vec![]);
+        ufw.entries = vec![(RuleEntry::from_numbered_row("[ 7] 22/tcp                     ALLOW IN    Anywhere").unwrap(), true)];
+
+        assert!(ufw.is_rule_present(&RuleEntry::allow_ssh()));
+        assert!(!ufw.is_rule_present(&RuleEntry::allow_http()));
+        assert!(!ufw.is_rule_present(&parse_rule_spec("deny 22/tcp").unwrap()));
+    }
+
+    #[test]
+    fn from_config_applies_the_executable_and_sudo_prefix() {
+        let command = UfwCommand::from_config(&config("command", "block_after_times = 3\nufw_executable = \"/usr/sbin/ufw\"\nsudo = true\n"));
+        assert_eq!(command.executable, PathBuf::from("/usr/sbin/ufw"));
+        assert_eq!(command.prefix, vec!["sudo"]);
+
+        let custom = UfwCommand::from_config(&config("command-custom", "block_after_times = 3\nsudo = \"doas -n\"\n"));
+        assert_eq!(custom.executable, PathBuf::from("/usr/bin/ufw"));
+        assert_eq!(custom.prefix, vec!["doas", "-n"]);
+
+        // the prefix runs the executable
+        let echo = UfwCommand { prefix: vec!["echo".to_string(), "ufw".to_string()], executable: PathBuf::from("0.36.1"), ..UfwCommand::new() };
+        assert_eq!(echo.version().unwrap(), "0.36.1");
+    }
+
+    #[test]
+    fn parse_info_defaults_a_never_enabled_firewall() {
+        let (enabled, logging) = parse_info("Status: inactive\n").unwrap();
+        assert!(!enabled);
+        assert_eq!(logging, LoggingLevel::Off);
+
+        assert!(matches!(parse_info("Status: active\n"), Err(InvalidLoggingLevel(_))));
+        assert_eq!(parse_info("Status: active\nLogging: on (medium)\n").unwrap(), (true, LoggingLevel::Medium));
+    }
+
+    #[test]
+    fn migration_script_collapses_ip_version_pairs() {
+        let row = |row: &str| (RuleEntry::from_numbered_row(row).unwrap(), true);
+        let mut ufw = pending(UfwCommand::new(), vec![]);
+        ufw.entries = vec![
+            row("[ 1] 22/tcp                     ALLOW IN    Anywhere"),
+            row("[ 2] 80/tcp                     ALLOW IN    10.0.0.0/8"),
+            row("[ 3] 22/tcp (v6)                ALLOW IN    Anywhere (v6)"),
+            row("[ 4] 443/tcp (v6)               ALLOW IN    Anywhere (v6)"),
+        ];
+
+        let script = ufw.migration_script();
+        let rules: Vec<&str> = script.lines().filter(|line| line.contains(" to ")).collect();
+        assert_eq!(rules, vec![
+            "ufw allow in proto tcp to any port 22",
+            "ufw allow in proto tcp from 10.0.0.0/8 to any port 80",
+            "ufw allow in proto tcp to any port 443",
+        ]);
+    }
+
+    #[test]
+    fn parse_with_version_reads_the_defaults_of_both_formats() {
+        let policy = |defaults: &DefaultPolicies| match defaults.incoming() {
+            Some(Ok(policy)) => *policy,
+            other => panic!("no incoming policy: {:?}", other),
+        };
+
+        let legacy = parse_with_version("Status: active\nDefault: deny\n", "0.31.1").unwrap();
+        assert_eq!(policy(&legacy), Some(RuleType::DENY));
+
+        let current = parse_with_version("Status: active\nDefault: reject (incoming), allow (outgoing), disabled (routed)\n", "0.36").unwrap();
+        assert_eq!(policy(&current), Some(RuleType::REJECT));
+        assert!(matches!(current.outgoing(), Some(Ok(Some(RuleType::ALLOW)))));
+        // the unlabeled legacy form isn't a policy for newer releases
+        assert!(parse_with_version("Default: deny\n", "0.36").unwrap().incoming().is_none());
+
+        assert!(matches!(parse_with_version("Default: deny\n", "zero"), Err(ParseError::InvalidVersion(_))));
+    }
+
+    #[test]
+    fn to_string_omits_the_defaults_ufw_allows() {
+        let full = RuleEntry {
+            interface: Some("tun0".to_string()),
+            source_address: Some(Address::try_from("192.168.1.0/24").unwrap()),
+            source_port: Some(1234),
+            proto: Protocol::TCP,
+            destination_port: Some(22),
+            ..RuleEntry::new(RuleType::ALLOW, RuleDirection::IN)
+        };
+        assert_eq!(full.to_string(), "allow in on tun0 proto tcp from 192.168.1.0/24 port 1234 to any port 22");
+
+        let port_only = RuleEntry {
+            destination_port: Some(80),
+            ..RuleEntry::new(RuleType::DENY, RuleDirection::IN)
+        };
+        assert_eq!(port_only.to_string(), "deny in to any port 80");
+
+        let any_protocol = RuleEntry {
+            proto: Protocol::ANY,
+            destination_port: Some(53),
+            ..RuleEntry::new(RuleType::ALLOW, RuleDirection::OUT)
+        };
+        assert_eq!(any_protocol.to_string(), "allow out to any port 53");
+    }
+
+    #[test]
+    fn parse_defaults_accepts_reordered_and_extra_entries() {
+        let defaults = parse_defaults("Status: active\nDefault: disabled (routed), reject (incoming), allow (outgoing), deny (bogus)\n").unwrap();
+
+        assert!(matches!(defaults.incoming(), Some(Ok(Some(RuleType::REJECT)))));
+        assert!(matches!(defaults.routed(), Some(Ok(None))));
+        assert_eq!(defaults.iter().map(DefaultPolicy::label).collect::<Vec<&str>>(), vec!["routed", "incoming", "outgoing", "bogus"]);
+        assert!(defaults.iter().last().unwrap().direction().is_err());
+    }
+
+    #[test]
+    fn address_validates_the_cidr_by_ip_version() {
+        let network = Address::try_from("2001:db8::/64").unwrap();
+        assert_eq!(network.cidr, 64);
+        assert_eq!(network.to_string(), "2001:db8::/64");
+
+        let host = Address::try_from("2001:db8::1/128").unwrap();
+        assert_eq!(host, Address::from(host.addr));
+        assert_eq!(host.to_string(), "2001:db8::1");
+
+        assert!(Address::try_from("2001:db8::/129").is_err());
+        assert!(Address::try_from("10.0.0.0/33").is_err());
+        assert_eq!(Address::from(IpAddr::from([10, 0, 0, 1])).cidr, 32);
+    }
+
+    #[test]
+    fn enable_forces_and_disable_reports_failures() {
+        let log = std::env::temp_dir().join(format!("ufw-parser-{}-enable.log", std::process::id()));
+        let command = stub_ufw("enable", &format!("echo \"$*\" >> {}", log.display()));
+        let _ = fs::remove_file(&log);
+
+        command.enable().unwrap();
+        command.disable().unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), "--force enable\ndisable\n");
+
+        let failing = stub_ufw("disable-failing", "echo 'ERROR: problem running' >&2; exit 1");
+        assert!(matches!(failing.disable(), Err(CommandError::Failed { code: Some(1), stderr }) if stderr.contains("problem running")));
+        assert!(failing.enable().is_err());
+    }
+
+    #[test]
+    fn reset_and_reload_report_failures() {
+        let working = stub_ufw("reset", r#"[ "$*" = "--force reset" ] || [ "$*" = "reload" ]"#);
+        assert!(working.reset().is_ok());
+        assert!(working.reload().is_ok());
+
+        let failing = stub_ufw("reset-failing", "echo 'ERROR: Could not reload' >&2; exit 1");
+        assert!(matches!(failing.reset(), Err(CommandError::Failed { code: Some(1), .. })));
+        assert!(matches!(failing.reload(), Err(CommandError::Failed { stderr, .. }) if stderr.contains("Could not reload")));
+    }
+
+    #[test]
+    fn status_numbered_reports_failures_per_row() {
+        let table = "Status: active\n\n     To                         Action      From\n     --                         ------      ----\n\
+            [ 1] 22/tcp                     ALLOW IN    Anywhere\n\
+            [ 2] 80/tcp on eth0             ALLOW IN    10.0.0.0/8\n\
+            [ 3] 5000/sctp                  ALLOW IN    Anywhere\n\
+            [ 4] 22/tcp (v6)                ALLOW IN    Anywhere (v6)\n";
+        let command = stub_ufw("status-numbered", &format!("printf '{}'", table));
+
+        let lines = command.status_numbered().unwrap();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1].as_ref().unwrap().in_interface(), Some("eth0"));
+        assert!(lines[2].is_err());
+        assert_eq!(RuleEntry::try_from(lines[3].as_ref().unwrap()).unwrap().ip_version, Some(IpVersion::V6));
+    }
+
+    #[test]
+    fn with_executable_chains_from_new() {
+        let path = stub_ufw("version", "echo 'ufw 0.36.1'; echo 'Copyright 2008-2021 Canonical Ltd.'").executable;
+
+        assert_eq!(UfwCommand::new().with_executable(&path).version().unwrap(), "0.36.1");
+        assert_eq!(UfwCommand::new().dry_run(true).with_executable(&path).executable, path);
+    }
+
+    #[test]
+    fn builder_builds_valid_rules_and_rejects_invalid_ones() {
+        let rule = RuleEntryBuilder::new().allow().direction_in().on("eth0").from_any().to_port(22).proto(Protocol::TCP).build().unwrap();
+        assert_eq!(rule.interface(), Some("eth0"));
+        assert_eq!((rule.destination_port(), rule.proto()), (Some(22), &Protocol::TCP));
+        assert_eq!(rule.to_string(), "allow in on eth0 proto tcp to any port 22");
+
+        let route = RuleEntryBuilder::new().allow().route().on("eth0").out_on("eth1").build().unwrap();
+        assert_eq!(route.to_string(), "route allow in on eth0 out on eth1 to any");
+
+        let rejected = |builder: RuleEntryBuilder| matches!(builder.build(), Err(ParseError::InvalidRuleSpec(_)));
+        assert!(rejected(RuleEntryBuilder::new().to_port(22)));
+        assert!(rejected(RuleEntryBuilder::new().allow().to_port(22).proto(Protocol::ESP)));
+        assert!(rejected(RuleEntryBuilder::new().allow().to_app("OpenSSH").proto(Protocol::TCP)));
+        assert!(rejected(RuleEntryBuilder::new().allow().on("eth0").out_on("eth1")));
+        assert!(rejected(RuleEntryBuilder::new().allow()
+            .from_address(Address::try_from("10.0.0.1").unwrap())
+            .to_address(Address::try_from("::1").unwrap())));
+    }
+
+    #[test]
+    fn ufw_port_renders_the_profile_syntax() {
+        let port = |number, end_number, protocols: &[Protocol]| UfwPort {
+            number,
+            end_number,
+            protocols: protocols.iter().cloned().map(Ok).collect(),
+        }.to_string();
+
+        assert_eq!(port(443, None, &[Protocol::TCP]), "443/tcp");
+        assert_eq!(port(6000, Some(6007), &[Protocol::UDP]), "6000:6007/udp");
+        assert_eq!(port(53, None, &[Protocol::TCP, Protocol::UDP]), "53");
+    }
+
+    #[test]
+    fn parse_rule_table_reads_plain_and_numbered_tables() {
+        let header = "Status: active\n\nTo                         Action      From\n--                         ------      ----\n";
+        let plain = format!("{}22/tcp                     ALLOW       Anywhere\n80/tcp                     DENY        10.0.0.0/8\n\
+            22/tcp (v6)                ALLOW       Anywhere (v6)\n", header);
+        let numbered = "Status: active\n\n     To                         Action      From\n     --                         ------      ----\n\
+            [ 1] 22/tcp                     ALLOW IN    Anywhere\n[ 2] 80/tcp                     DENY IN     10.0.0.0/8\n";
+
+        let rules = parse_rule_table(&plain);
+        assert_eq!(rules.len(), 3);
+        assert!(rules.iter().all(Result::is_ok));
+        assert_eq!(rules[1].as_ref().unwrap().destination_port(), Some(80));
+        assert_eq!(parse_rule_table(numbered).iter().flatten().count(), 2);
+
+        let command = stub_ufw("status-plain", &format!("printf '{}'", plain));
+        assert_eq!(command.status().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn parses_captured_status_verbose_output() {
+        let verbose = "Status: active\nLogging: on (low)\nDefault: deny (incoming), allow (outgoing), disabled (routed)\n\
+            New profiles: skip\n\nTo                         Action      From\n--                         ------      ----\n\
+            22/tcp                     ALLOW IN    Anywhere\n22/tcp (v6)                ALLOW IN    Anywhere (v6)\n";
+
+        assert_eq!(parse_info(verbose).unwrap(), (true, LoggingLevel::Low));
+        let defaults = parse_defaults(verbose).unwrap();
+        assert!(matches!(defaults.incoming(), Some(Ok(Some(RuleType::DENY)))));
+        assert!(matches!(defaults.outgoing(), Some(Ok(Some(RuleType::ALLOW)))));
+        assert!(matches!(defaults.routed(), Some(Ok(None))));
+
+        assert_eq!(parse_version("ufw 0.36.1\nCopyright 2008-2021 Canonical Ltd.\n").unwrap(), "0.36.1");
+        assert!(parse_version("command not found").is_err());
+        assert!(parse_defaults("Default: deny (incoming)\n").is_err());
+    }
+
+    #[test]
+    fn delete_rule_rejects_indices_past_the_end() {
+        let rule = |port| RuleEntryBuilder::new().allow().to_port(port).build().unwrap();
+        let mut ufw = pending(UfwCommand::new(), vec![rule(22), rule(80)]);
+
+        assert!(ufw.delete_rule(2).is_none());
+        let (deleted, committed) = ufw.delete_rule(1).unwrap();
+        assert_eq!((deleted.destination_port(), committed), (Some(80), false));
+        assert_eq!(ufw.entries.len(), 1);
+
+        let mut empty = pending(UfwCommand::new(), vec![]);
+        assert!(empty.delete_rule(0).is_none());
+    }
+
+    #[test]
+    fn from_system_reads_the_live_firewall_as_committed() {
+        let log = std::env::temp_dir().join(format!("ufw-parser-{}-from-system.log", std::process::id()));
+        let _ = fs::remove_file(&log);
+        let script = format!("case \"$*\" in\n  'status verbose') cat <<'X'\nStatus: active\nLogging: on (medium)\n\
+            Default: deny (incoming), allow (outgoing), disabled (routed)\nNew profiles: skip\nX\n  ;;\n\
+            'status numbered') cat <<'X'\nStatus: active\n\n     To                         Action      From\n     --                         ------      ----\n\
+            [ 1] 22/tcp                     ALLOW IN    Anywhere\n[ 2] 80/tcp                     DENY IN     10.0.0.0/8\nX\n  ;;\n\
+            *) echo \"$*\" >> {};;\nesac", log.display());
+        let command = stub_ufw("from-system", &script);
+
+        let mut ufw = Ufw::from_system(&command).unwrap();
+        assert_eq!((ufw.enabled(), ufw.logging()), (true, LoggingLevel::Medium));
+        assert!(matches!(ufw.defaults().incoming(), Some(Ok(Some(RuleType::DENY)))));
+        let ports: Vec<Option<u16>> = ufw.entries().map(RuleEntry::destination_port).collect();
+        assert_eq!(ports, vec![Some(22), Some(80)]);
+
+        // only the new rule is submitted, the ones read from ufw already exist
+        ufw.add_rule(RuleEntryBuilder::new().allow().to_port(443).proto(Protocol::TCP).build().unwrap());
+        ufw.submit().unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), "allow in proto tcp to any port 443\n");
+    }
+
+    #[test]
+    fn from_system_keeps_ranges_and_reports_unrepresented_rows() {
+        let log = std::env::temp_dir().join(format!("ufw-parser-{}-from-system-ranges.log", std::process::id()));
+        let _ = fs::remove_file(&log);
+        let script = format!("case \"$*\" in\n  'status verbose') cat <<'X'\nStatus: active\nLogging: on (low)\n\
+            Default: deny (incoming), allow (outgoing), disabled (routed)\nNew profiles: skip\nX\n  ;;\n\
+            'status numbered') cat <<'X'\nStatus: active\n\n     To                         Action      From\n     --                         ------      ----\n\
+            [ 1] 6000:6007/tcp              ALLOW IN    Anywhere\n[ 2] 80,443/tcp                 ALLOW IN    Anywhere\n\
+            [ 3] 22/tcp                     ALLOW IN    Anywhere\nX\n  ;;\n\
+            *) echo \"$*\" >> {};;\nesac", log.display());
+        let command = stub_ufw("from-system-ranges", &script);
+
+        let mut ufw = Ufw::from_system(&command).unwrap();
+        let rules: Vec<String> = ufw.entries().map(RuleEntry::to_string).collect();
+        assert_eq!(rules, vec!["allow in proto tcp to any port 6000:6007", "allow in proto tcp to any port 22"]);
+        assert_eq!(ufw.unrepresented().len(), 1);
+        assert!(matches!(&ufw.unrepresented()[0], (2, ParseError::InvalidStatusRow(row)) if row.starts_with("rule 2")));
+
+        // new rules keep their place in between the rows ufw has, the unrepresented one included
+        ufw.entries.insert(1, (RuleEntry::allow_http(), false));
+        ufw.entries.insert(0, (RuleEntry::allow_https(), false));
+        ufw.submit().unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), "insert 1 allow in proto tcp to any port 443\ninsert 4 allow in proto tcp to any port 80\n");
+        assert_eq!(ufw.unrepresented()[0].0, 3);
+    }
+
+    #[test]
+    fn clear_user_rules_deletes_the_highest_number_first() {
+        let log = std::env::temp_dir().join(format!("ufw-parser-{}-clear.log", std::process::id()));
+        let _ = fs::remove_file(&log);
+        let script = format!(r#"case "$1" in
+            status) printf '[ 1] 22/tcp  ALLOW IN  Anywhere\n[ 3] 80/tcp  ALLOW IN  Anywhere\n[ 2] 443/tcp  ALLOW IN  Anywhere\n';;
+            *) echo "$*" >> {};;
+        esac"#, log.display());
+        let ufw = pending(stub_ufw("clear", &script), vec![]);
+
+        ufw.clear_user_rules().unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), "--force delete 3\n--force delete 2\n--force delete 1\n");
+    }
+
+    #[test]
+    fn rules_differing_only_in_their_number_are_equal() {
+        let rule = RuleEntryBuilder::new().allow().on("eth0").to_port(22).proto(Protocol::TCP).build().unwrap();
+        let numbered = RuleEntry { number: 7, ..rule.clone() };
+
+        assert_eq!(rule, numbered);
+        assert_ne!(rule, RuleEntry { destination_port: Some(2222), ..rule.clone() });
+        assert_ne!(rule, RuleEntry { proto: Protocol::UDP, ..rule.clone() });
+    }
+
+    #[test]
+    fn rules_are_the_same_to_is_rule_present_apply_desired_and_hash_sets() {
+        let table = "[ 1] 22/tcp                     ALLOW IN    Anywhere                   # ssh\n\
+            [ 2] 22/tcp (v6)                ALLOW IN    Anywhere (v6)              # ssh";
+        let (command, log) = logging_stub("same-rule", table);
+        let current = command.rules().unwrap().into_iter().collect::<ParseResult<Vec<RuleEntry>>>().unwrap();
+        let ufw = pending(command, vec![]);
+        let mut ufw_with_current = pending(UfwCommand::new(), vec![]);
+        ufw_with_current.entries = current.iter().cloned().map(|entry| (entry, true)).collect();
+
+        // the v4 and v6 row are one rule, a different comment makes another
+        assert_eq!(current.iter().collect::<HashSet<&RuleEntry>>().len(), 1);
+        let commented = parse_rule_spec("allow 22/tcp comment ssh").unwrap();
+        let uncommented = parse_rule_spec("allow 22/tcp").unwrap();
+        assert!(ufw_with_current.is_rule_present(&commented));
+        assert!(!ufw_with_current.is_rule_present(&uncommented));
+
+        let report = ufw.apply_desired(&[commented]).unwrap();
+        assert!(report.added().is_empty() && report.deleted().is_empty());
+        let report = ufw.apply_desired(&[uncommented]).unwrap();
+        assert_eq!((report.added().len(), report.deleted().len()), (1, 1));
+        assert_eq!(fs::read_to_string(&log).unwrap(), "allow in proto tcp to any port 22\n--force delete allow in proto tcp to any port 22 comment ssh\n");
+    }
+
+    #[test]
+    fn port_ranges_round_trip_and_need_a_protocol() {
+        let rule = parse_rule_spec("allow 6000:6007/tcp").unwrap();
+        assert_eq!((rule.destination_port(), rule.destination_end_port()), (Some(6000), Some(6007)));
+        assert_eq!(rule.to_string(), "allow in proto tcp to any port 6000:6007");
+        assert_eq!(parse_rule_spec(&rule.to_string()).unwrap(), rule);
+        assert_eq!(RuleEntryBuilder::new().allow().proto(Protocol::TCP).to_port_range(6000, 6007).build().unwrap(), rule);
+        assert!(rule.covers(&parse_rule_spec("allow 6003/tcp").unwrap()));
+        assert!(!rule.covers(&parse_rule_spec("allow 5999:6001/tcp").unwrap()));
+
+        assert!(parse_rule_spec("allow 6000:6007").is_err());
+        assert!(matches!(parse_rule_spec("allow 6007:6000/tcp"), Err(ParseError::InvalidPortRange(range)) if range == "6007:6000"));
+        assert!(RuleEntryBuilder::new().allow().to_port_range(6000, 6007).build().is_err());
+        assert!(RuleEntryBuilder::new().allow().proto(Protocol::UDP).from_port_range(6007, 6000).build().is_err());
+    }
+
+    #[test]
+    fn set_logging_takes_the_levels_parse_info_reads() {
+        let (command, log) = logging_stub("set-logging", "");
+        let statuses = ["Logging: off", "Logging: on (low)", "Logging: on (medium)", "Logging: on (high)", "Logging: on (full)"];
+
+        for status in statuses.iter() {
+            let (_, level) = parse_info(&format!("Status: active\n{}\n", status)).unwrap();
+            command.set_logging(level).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&log).unwrap(), "logging off\nlogging low\nlogging medium\nlogging high\nlogging full\n");
+    }
+
+    #[test]
+    fn dry_run_submit_keeps_rules_pending() {
+        let mut ufw = pending(stub_ufw("dry-run", "true").dry_run(true), vec![RuleEntryBuilder::new().allow().to_port(22).build().unwrap()]);
+
+        ufw.submit().unwrap();
+        assert!(!ufw.entries[0].1);
+    }
+
+    #[test]
+    fn dry_run_prepends_the_flag_to_every_invocation() {
+        let (command, log) = logging_stub("dry-run-flag", "");
+        let command = command.dry_run(true);
+
+        command.enable().unwrap();
+        command.add_rule(&RuleEntryBuilder::new().allow().to_port(22).build().unwrap()).unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), "--dry-run --force enable\n--dry-run allow in to any port 22\n");
+
+        let (command, log) = logging_stub("without-dry-run", "");
+        command.dry_run(false).disable().unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), "disable\n");
+    }
+
+    #[test]
+    fn raw_status_returns_the_verbatim_output() {
+        let verbose = "Status: active\nLogging: on (low)\nDefault: deny (incoming), allow (outgoing), disabled (routed)\n";
+        let command = stub_ufw("raw-status", &format!("[ \"$*\" = 'status verbose' ] && printf '{}'", verbose));
+        assert_eq!(command.raw_status().unwrap(), verbose);
+
+        let binary = stub_ufw("raw-status-binary", "printf '\\377\\376'");
+        assert!(matches!(binary.raw_status(), Err(CommandError::Parse(ParseError::IOError(_)))));
+    }
+
+    #[test]
+    fn limit_rules_carry_ufws_rate() {
+        let rule = RuleEntry::from_numbered_row("[ 1] 22/tcp                     LIMIT IN    Anywhere").unwrap();
+        let rate = rule.rate_limit().unwrap();
+        assert_eq!((rate.hits(), rate.seconds()), (6, 30));
+
+        let allow = RuleEntry::from_numbered_row("[ 2] 22/tcp                     ALLOW IN    Anywhere").unwrap();
+        assert_eq!(allow.rate_limit(), None);
+    }
+
+    #[test]
+    fn failed_commands_keep_their_exit_code() {
+        let conflict = stub_ufw("exit-code", "echo 'ERROR: Could not update running firewall' >&2; exit 3");
+        for result in [conflict.info().map(|_| ()), conflict.defaults().map(|_| ()), conflict.reload()].iter() {
+            assert!(matches!(result, Err(CommandError::Failed { code: Some(3), stderr }) if stderr == "ERROR: Could not update running firewall"));
+        }
+
+        let killed = stub_ufw("exit-signal", "kill -9 $$");
+        assert!(matches!(killed.info(), Err(CommandError::Failed { code: None, .. })));
+    }
+
+    #[test]
+    fn submit_atomic_stops_at_the_first_failure() {
+        let command = stub_ufw("submit-atomic", r#"case "$*" in *23*) echo "ERROR: rejected" >&2; exit 1;; esac"#);
+        let mut ufw = pending(command, [22, 23, 24].iter().map(|&port| RuleEntryBuilder::new().allow().to_port(port).build().unwrap()).collect());
+
+        match ufw.submit_atomic() {
+            Err(CommandError::PartialSubmit { applied, failed, source }) => {
+                assert_eq!(applied, vec![0]);
+                assert_eq!(failed, 1);
+                assert!(matches!(*source, CommandError::Failed { code: Some(1), .. }));
+            }
+            other => panic!("expected a partial submit, got {:?}", other),
         }
+        assert_eq!(ufw.entries.iter().filter(|(_, committed)| *committed).count(), 1);
+    }
+
+    #[test]
+    fn app_list_and_app_info_parse_ufws_output() {
+        let script = "case \"$*\" in\n  'app list') printf 'Available applications:\\n  Nginx Full\\n  OpenSSH\\n';;\n\
+            'app info Games') cat <<'X'\nProfile: Games\nTitle: Game servers\nDescription: Servers of several\n games\n\nPorts:\n  80,443/tcp\n  27000:27015/udp\nX\n;;\n\
+            *) exit 1;;\nesac";
+        let command = stub_ufw("app", script);
+
+        assert_eq!(command.app_list().unwrap(), vec!["Nginx Full", "OpenSSH"]);
+
+        let entry = command.app_info("Games").unwrap();
+        assert_eq!((entry.name.as_str(), entry.title.as_str()), ("Games", "Game servers"));
+        assert_eq!(entry.ports_as_string(), "80/tcp|443/tcp|27000:27015/udp");
+        assert!(command.app_info("Missing").is_err());
+    }
+
+    #[test]
+    fn apply_desired_handles_ranges_and_profiles_like_the_numbered_parser() {
+        let (command, log) = logging_stub("apply-desired-ranges", "[ 1] 6000:6007/tcp              ALLOW IN    Anywhere\n\
+            [ 2] Nginx Full                 ALLOW IN    Anywhere\n\
+            [ 3] 6000:6007/tcp (v6)         ALLOW IN    Anywhere (v6)\n\
+            [ 4] Nginx Full (v6)            ALLOW IN    Anywhere (v6)");
+        let ufw = pending(command, vec![]);
+        let desired = [parse_rule_spec("allow 6000:6007/tcp").unwrap(), parse_rule_spec("allow 22/tcp").unwrap()];
+
+        let report = ufw.apply_desired(&desired).unwrap();
+        assert_eq!((report.added().len(), report.deleted().len()), (1, 1));
+        assert_eq!(fs::read_to_string(&log).unwrap(), "allow in proto tcp to any port 22\n--force delete allow in to any app Nginx Full\n");
+
+        let plain = "Status: active\n\nTo                         Action      From\n--                         ------      ----\n\
+            6000:6007/tcp              ALLOW       Anywhere\nNginx Full                 ALLOW       Anywhere\n";
+        let rules = parse_rule_table(plain).into_iter().collect::<ParseResult<Vec<RuleEntry>>>().unwrap();
+        assert_eq!(rules, vec![desired[0].clone(), parse_rule_spec("allow 'Nginx Full'").unwrap()]);
+    }
+
+    #[test]
+    fn info_reports_an_inactive_firewall_as_disabled() {
+        let command = stub_ufw("info-inactive", "echo 'Status: inactive'");
+
+        assert_eq!(command.info().unwrap(), (false, LoggingLevel::Off));
     }
 
-    fn exec(&self, args: Vec<&str>) -> io::Result<UfwCommandOutput> {
-        Command::new(&self.executable)
-            .args(args)
-            .output()
+    #[test]
+    fn parse_info_tells_active_and_inactive_apart() {
+        assert!(parse_info("Status: active\nLogging: off\n").unwrap().0);
+        assert!(!parse_info("Status: inactive\nLogging: on (high)\n").unwrap().0);
+        assert_eq!(parse_info("Status: inactive\nLogging: on (high)\n").unwrap().1, LoggingLevel::High);
+        assert!(parse_info("Status: unknown\n").is_err());
+    }
+
+    #[test]
+    fn set_default_runs_ufw_default_and_rejects_limit() {
+        let (command, log) = logging_stub("set-default", "");
+
+        command.set_default(RuleDirectionDefaults::INCOMING, RuleType::DENY).unwrap();
+        command.set_default(RuleDirectionDefaults::ROUTED, RuleType::REJECT).unwrap();
+        let limit = command.set_default(RuleDirectionDefaults::OUTGOING, RuleType::LIMIT);
+        assert!(matches!(limit, Err(CommandError::Parse(ParseError::WrongRuleType(_)))));
+
+        // the rejected policy never reaches ufw
+        assert_eq!(fs::read_to_string(&log).unwrap(), "default deny incoming\ndefault reject routed\n");
     }
 }
@@ -0,0 +1,94 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::application::{Application, parse_applications};
+use crate::config::{UfwConf, UfwDefaults};
+use crate::ParseResult;
+
+// the file a change happened in, parsed again
+#[derive(Debug)]
+pub enum UfwChange {
+    Applications(io::Result<Vec<ParseResult<Application>>>),
+    Conf(ParseResult<UfwConf>),
+    Defaults(ParseResult<UfwDefaults>),
+    // the watcher itself failed, no file was parsed
+    Error(notify::Error),
+}
+
+// re-parses the application profiles (`<path>/applications.d`), `<path>/ufw.conf` or the defaults file
+// (`/etc/default/ufw` if none is given) whenever one of them changes.
+// watching stops once the returned watcher is dropped
+pub fn watch_ufw_dir<P, D, F>(path: P, defaults_file: Option<D>, mut callback: F) -> notify::Result<RecommendedWatcher>
+    where P: AsRef<Path>,
+          D: Into<PathBuf>,
+          F: FnMut(UfwChange) + Send + 'static {
+    let applications_directory: PathBuf = path.as_ref().join("applications.d");
+    let conf_file: PathBuf = path.as_ref().join("ufw.conf");
+    let defaults_file: PathBuf = defaults_file.map(Into::into).unwrap_or_else(|| "/etc/default/ufw".into());
+    let watched_defaults = defaults_file.clone();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        match event {
+            Ok(event) if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() => {
+                let changed = |matches: &dyn Fn(&PathBuf) -> bool| event.paths.iter().any(matches);
+
+                if changed(&|path| path.starts_with(&applications_directory)) {
+                    callback(UfwChange::Applications(parse_applications(Some(&applications_directory))))
+                }
+                if changed(&|path| path == &conf_file) {
+                    callback(UfwChange::Conf(UfwConf::from_file(&conf_file)))
+                }
+                if changed(&|path| path == &defaults_file) {
+                    callback(UfwChange::Defaults(UfwDefaults::from_file(&defaults_file)))
+                }
+            }
+            Ok(_) => {}
+            Err(err) => callback(UfwChange::Error(err)),
+        }
+    })?;
+    watcher.watch(path.as_ref(), RecursiveMode::Recursive)?;
+    // editors replace files instead of writing to them, so the directory of the defaults is watched
+    if let Some(directory) = watched_defaults.parent().filter(|directory| !directory.starts_with(path.as_ref())) {
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+    }
+
+    Ok(watcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn reparses_the_changed_file() {
+        let root = std::env::temp_dir().join(format!("ufw-parser-{}-watch", std::process::id()));
+        let directory = root.join("ufw");
+        fs::create_dir_all(directory.join("applications.d")).unwrap();
+        fs::create_dir_all(root.join("default")).unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let _watcher = watch_ufw_dir(&directory, Some(root.join("default/ufw")), move |change| { let _ = sender.send(change); }).unwrap();
+        // writing a file can be reported as several events, the first ones may still see it empty
+        let wait_for = |expected: &dyn Fn(&UfwChange) -> bool| loop {
+            let change = receiver.recv_timeout(Duration::from_secs(5)).expect("no matching change");
+            if expected(&change) {
+                break;
+            }
+        };
+
+        fs::write(directory.join("ufw.conf"), "ENABLED=yes\nLOGLEVEL=low\n").unwrap();
+        wait_for(&|change| matches!(change, UfwChange::Conf(Ok(conf)) if conf.enabled()));
+
+        fs::write(root.join("default/ufw"), "IPV6=yes\n").unwrap();
+        wait_for(&|change| matches!(change, UfwChange::Defaults(Ok(_))));
+
+        fs::write(directory.join("applications.d/openssh"), "[OpenSSH]\ntitle=ssh\ndescription=ssh\nports=22/tcp\n").unwrap();
+        wait_for(&|change| matches!(change, UfwChange::Applications(Ok(applications)) if applications.len() == 1));
+    }
+}